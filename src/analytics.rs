@@ -0,0 +1,213 @@
+use crate::bot::{BotRun, Policy};
+use crate::events::{DeathCause, DeathEvent, SpawnEvent};
+use crate::sim::SimConfig;
+use crate::world::{CharType, Difficulty};
+use bevy::app::{Events, ManualEventReader};
+use serde::Serialize;
+use std::fmt::Write;
+
+// How one headless game went.
+pub struct GameRecord {
+    pub seed: u64,
+    pub time: f32,
+    pub score: u32,
+    // `None` when the player was still alive when the game was cut off.
+    pub cause: Option<DeathCause>,
+    pub spawn_times: Vec<f32>,
+}
+
+// Plays like `bot::play`, but also keeps track of the spawns and what the player died to.
+pub fn record_game(
+    policy: &mut dyn Policy,
+    config: SimConfig,
+    seed: u64,
+    max_ticks: u64,
+) -> GameRecord {
+    let mut run = BotRun::new(config, seed);
+    let mut spawns = ManualEventReader::<SpawnEvent>::default();
+    let mut deaths = ManualEventReader::<DeathEvent>::default();
+    let mut record = GameRecord {
+        seed,
+        time: 0f32,
+        score: 0,
+        cause: None,
+        spawn_times: Vec::new(),
+    };
+    loop {
+        let result = run.result();
+        record.time = result.time;
+        record.score = result.score;
+        if result.finished || result.ticks >= max_ticks {
+            return record;
+        }
+        if let Some(aim) = policy.act(&run.observe()) {
+            run.press(aim);
+        }
+        run.step();
+        let time = run.result().time;
+        let world = run.sim().world();
+        let spawn_events = world.get_resource::<Events<SpawnEvent>>().unwrap();
+        record
+            .spawn_times
+            .extend(spawns.iter(spawn_events).map(|_| time));
+        let death_events = world.get_resource::<Events<DeathEvent>>().unwrap();
+        for death in deaths.iter(death_events) {
+            if death.char_type == CharType::Player {
+                record.cause = Some(death.cause);
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Bucket {
+    pub start: f32,
+    pub value: f32,
+}
+
+#[derive(Serialize)]
+pub struct CauseCount {
+    pub cause: Option<DeathCause>,
+    pub games: usize,
+}
+
+// Outcomes of a batch of games on one difficulty, bucketed by time into the run.
+#[derive(Serialize)]
+pub struct Report {
+    pub difficulty: Difficulty,
+    pub games: usize,
+    pub mean: f32,
+    pub p10: f32,
+    pub median: f32,
+    pub p90: f32,
+    pub best: f32,
+    // Games that ended in every bucket.
+    pub survival_histogram: Vec<Bucket>,
+    // Share of the games still going at the start of every bucket.
+    pub survival_curve: Vec<Bucket>,
+    // What the player died to, `None` for games that were cut off.
+    pub death_causes: Vec<CauseCount>,
+    // Spawns per second in every bucket, over the games still going.
+    pub spawn_rate: Vec<Bucket>,
+}
+
+fn percentile(sorted: &[f32], fraction: f32) -> f32 {
+    sorted[((sorted.len() - 1) as f32 * fraction).round() as usize]
+}
+
+impl Report {
+    pub fn new(difficulty: Difficulty, records: &[GameRecord], bucket: f32, max_time: f32) -> Self {
+        let mut times: Vec<f32> = records.iter().map(|record| record.time).collect();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let games = times.len().max(1);
+        let buckets = (max_time / bucket).ceil() as usize;
+        let mut survival_histogram = Vec::new();
+        let mut survival_curve = Vec::new();
+        let mut spawn_rate = Vec::new();
+        for index in 0..buckets {
+            let start = index as f32 * bucket;
+            let end = start + bucket;
+            let ended = records
+                .iter()
+                .filter(|record| record.cause.is_some())
+                .filter(|record| record.time >= start && record.time < end)
+                .count();
+            let going = times.iter().filter(|time| **time >= start).count();
+            let spawned: usize = records
+                .iter()
+                .map(|record| {
+                    record
+                        .spawn_times
+                        .iter()
+                        .filter(|time| **time >= start && **time < end)
+                        .count()
+                })
+                .sum();
+            survival_histogram.push(Bucket {
+                start,
+                value: ended as f32,
+            });
+            survival_curve.push(Bucket {
+                start,
+                value: going as f32 / games as f32,
+            });
+            spawn_rate.push(Bucket {
+                start,
+                value: if going > 0 {
+                    spawned as f32 / going as f32 / bucket
+                } else {
+                    0f32
+                },
+            });
+        }
+        let death_causes = DeathCause::ALL
+            .iter()
+            .map(|cause| Some(*cause))
+            .chain(std::iter::once(None))
+            .map(|cause| CauseCount {
+                cause,
+                games: records
+                    .iter()
+                    .filter(|record| record.cause == cause)
+                    .count(),
+            })
+            .collect();
+        let (mean, p10, median, p90, best) = if times.is_empty() {
+            (0f32, 0f32, 0f32, 0f32, 0f32)
+        } else {
+            (
+                times.iter().sum::<f32>() / times.len() as f32,
+                percentile(&times, 0.1f32),
+                percentile(&times, 0.5f32),
+                percentile(&times, 0.9f32),
+                times[times.len() - 1],
+            )
+        };
+        Report {
+            difficulty,
+            games: times.len(),
+            mean,
+            p10,
+            median,
+            p90,
+            best,
+            survival_histogram,
+            survival_curve,
+            death_causes,
+            spawn_rate,
+        }
+    }
+}
+
+fn cause_label(cause: Option<DeathCause>) -> String {
+    cause.map_or("survived".to_string(), |cause| format!("{:?}", cause))
+}
+
+// One row per number, so reports of several difficulties go in the same table.
+pub fn to_csv(reports: &[Report]) -> String {
+    let mut csv = "difficulty,metric,key,value\n".to_string();
+    for report in reports.iter() {
+        let mut row = |metric: &str, key: String, value: f32| {
+            writeln!(csv, "{:?},{},{},{}", report.difficulty, metric, key, value).unwrap();
+        };
+        row("games", String::new(), report.games as f32);
+        row("mean", String::new(), report.mean);
+        row("p10", String::new(), report.p10);
+        row("median", String::new(), report.median);
+        row("p90", String::new(), report.p90);
+        row("best", String::new(), report.best);
+        for bucket in report.survival_histogram.iter() {
+            row("survival_histogram", bucket.start.to_string(), bucket.value);
+        }
+        for bucket in report.survival_curve.iter() {
+            row("survival_curve", bucket.start.to_string(), bucket.value);
+        }
+        for count in report.death_causes.iter() {
+            row("death_cause", cause_label(count.cause), count.games as f32);
+        }
+        for bucket in report.spawn_rate.iter() {
+            row("spawn_rate", bucket.start.to_string(), bucket.value);
+        }
+    }
+    csv
+}
@@ -1,17 +1,237 @@
-/*
+use crate::events::{DeathEvent, FireEvent, SpawnEvent, ThrustEvent};
+use crate::world::CharType;
 use bevy::prelude::*;
 
-pub struct AudioResource {
-    music: Handle<AudioSource>,
+const MUSIC_PATH: &str = "audio/music.wav";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Sfx {
+    Thrust,
+    Fire,
+    EnemySpawn,
+    EnemyDeath,
+    PlayerDeath,
+}
+
+impl Sfx {
+    const ALL: [Sfx; 5] = [
+        Sfx::Thrust,
+        Sfx::Fire,
+        Sfx::EnemySpawn,
+        Sfx::EnemyDeath,
+        Sfx::PlayerDeath,
+    ];
+
+    fn path(self) -> &'static str {
+        match self {
+            Sfx::Thrust => "audio/thrust.wav",
+            Sfx::Fire => "audio/fire.wav",
+            Sfx::EnemySpawn => "audio/enemy_spawn.wav",
+            Sfx::EnemyDeath => "audio/enemy_death.wav",
+            Sfx::PlayerDeath => "audio/player_death.wav",
+        }
+    }
+}
+
+pub struct SfxQueue(Vec<Sfx>);
+
+// Browsers refuse to start audio until the page has received a user gesture.
+pub struct AudioUnlocked(pub bool);
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(SfxQueue(Vec::new()))
+            .insert_resource(AudioUnlocked(!cfg!(target_arch = "wasm32")))
+            .add_system(unlock_audio.system())
+            .add_system(queue_sfx.system());
+        backend::build(app);
+    }
+}
+
+pub fn unlock_audio(
+    mut unlocked: ResMut<AudioUnlocked>,
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+) {
+    if !unlocked.0 && (mouse.get_just_pressed().len() > 0 || keys.get_just_pressed().len() > 0) {
+        unlocked.0 = true;
+    }
+}
+
+pub fn queue_sfx(
+    mut queue: ResMut<SfxQueue>,
+    mut thrusts: EventReader<ThrustEvent>,
+    mut fires: EventReader<FireEvent>,
+    mut spawns: EventReader<SpawnEvent>,
+    mut deaths: EventReader<DeathEvent>,
+) {
+    queue.0.extend(thrusts.iter().map(|_| Sfx::Thrust));
+    queue.0.extend(fires.iter().map(|_| Sfx::Fire));
+    queue.0.extend(spawns.iter().map(|_| Sfx::EnemySpawn));
+    for death in deaths.iter() {
+        match death.char_type {
+            CharType::Player => queue.0.push(Sfx::PlayerDeath),
+            CharType::Enemy => queue.0.push(Sfx::EnemyDeath),
+            CharType::Bullet => {}
+        }
+    }
 }
 
-pub fn load_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let music: Handle<AudioSource> = asset_server.load("Rhythm 3.mp3");
-    let resource = AudioResource { music };
-    commands.insert_resource(resource);
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use super::{AudioUnlocked, Sfx, SfxQueue, MUSIC_PATH};
+    use crate::settings::Settings;
+    use bevy::prelude::*;
+    use bevy::utils::HashMap;
+    use bevy_kira_audio::{Audio, AudioChannel, AudioPlugin, AudioSource};
+
+    pub struct AudioResource {
+        music: Handle<AudioSource>,
+        sfx: HashMap<Sfx, Handle<AudioSource>>,
+        music_channel: AudioChannel,
+        sfx_channel: AudioChannel,
+        music_playing: bool,
+    }
+
+    pub fn build(app: &mut AppBuilder) {
+        app.add_plugin(AudioPlugin)
+            .add_startup_system(load_audio.system())
+            .add_system(play_audio.system())
+            .add_system(play_sfx.system())
+            .add_system(apply_volume.system());
+    }
+
+    pub fn load_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
+        let music: Handle<AudioSource> = asset_server.load(MUSIC_PATH);
+        let sfx = Sfx::ALL
+            .iter()
+            .map(|sfx| (*sfx, asset_server.load(sfx.path())))
+            .collect();
+        commands.insert_resource(AudioResource {
+            music,
+            sfx,
+            music_channel: AudioChannel::new("music".to_string()),
+            sfx_channel: AudioChannel::new("sfx".to_string()),
+            music_playing: false,
+        });
+    }
+
+    pub fn play_audio(
+        unlocked: Res<AudioUnlocked>,
+        audio: Res<Audio>,
+        mut resource: ResMut<AudioResource>,
+    ) {
+        if unlocked.0 && !resource.music_playing {
+            audio.play_looped_in_channel(resource.music.clone(), &resource.music_channel);
+            resource.music_playing = true;
+        }
+    }
+
+    pub fn play_sfx(
+        unlocked: Res<AudioUnlocked>,
+        audio: Res<Audio>,
+        resource: Res<AudioResource>,
+        mut queue: ResMut<SfxQueue>,
+    ) {
+        for sfx in queue.0.drain(..) {
+            if let (true, Some(handle)) = (unlocked.0, resource.sfx.get(&sfx)) {
+                audio.play_in_channel(handle.clone(), &resource.sfx_channel);
+            }
+        }
+    }
+
+    pub fn apply_volume(settings: Res<Settings>, audio: Res<Audio>, resource: Res<AudioResource>) {
+        if settings.is_changed() || resource.is_added() {
+            audio.set_volume_in_channel(settings.music_gain(), &resource.music_channel);
+            audio.set_volume_in_channel(settings.sfx_gain(), &resource.sfx_channel);
+        }
+    }
 }
 
-pub fn play_audio(resource: Res<AudioResource>, audio: Res<Audio>) {
-    audio.play(resource.music.clone());
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use super::{AudioUnlocked, Sfx, SfxQueue, MUSIC_PATH};
+    use crate::settings::Settings;
+    use bevy::prelude::*;
+    use bevy::utils::HashMap;
+    use wasm_bindgen::JsCast;
+    use web_sys::HtmlAudioElement;
+
+    pub struct AudioResource {
+        music: Option<HtmlAudioElement>,
+        sfx: HashMap<Sfx, HtmlAudioElement>,
+        music_playing: bool,
+    }
+
+    fn element(path: &str) -> Option<HtmlAudioElement> {
+        HtmlAudioElement::new_with_src(&format!("assets/{}", path)).ok()
+    }
+
+    pub fn build(app: &mut AppBuilder) {
+        app.insert_non_send_resource(load_audio())
+            .add_system(play_audio.system())
+            .add_system(play_sfx.system())
+            .add_system(apply_volume.system());
+    }
+
+    pub fn load_audio() -> AudioResource {
+        let music = element(MUSIC_PATH);
+        if let Some(music) = &music {
+            music.set_loop(true);
+        }
+        let sfx = Sfx::ALL
+            .iter()
+            .filter_map(|sfx| Some((*sfx, element(sfx.path())?)))
+            .collect();
+        AudioResource {
+            music,
+            sfx,
+            music_playing: false,
+        }
+    }
+
+    pub fn play_audio(
+        unlocked: Res<AudioUnlocked>,
+        settings: Res<Settings>,
+        mut resource: NonSendMut<AudioResource>,
+    ) {
+        if unlocked.0 && !resource.music_playing {
+            if let Some(music) = &resource.music {
+                music.set_volume(settings.music_gain() as f64);
+                music.play().ok();
+            }
+            resource.music_playing = true;
+        }
+    }
+
+    pub fn play_sfx(
+        unlocked: Res<AudioUnlocked>,
+        settings: Res<Settings>,
+        resource: NonSend<AudioResource>,
+        mut queue: ResMut<SfxQueue>,
+    ) {
+        for sfx in queue.0.drain(..) {
+            if let (true, Some(source)) = (unlocked.0, resource.sfx.get(&sfx)) {
+                // Cloning the element lets the same effect overlap with itself.
+                let instance = source
+                    .clone_node()
+                    .ok()
+                    .and_then(|node| node.dyn_into::<HtmlAudioElement>().ok());
+                if let Some(instance) = instance {
+                    instance.set_volume(settings.sfx_gain() as f64);
+                    instance.play().ok();
+                }
+            }
+        }
+    }
+
+    pub fn apply_volume(settings: Res<Settings>, resource: NonSend<AudioResource>) {
+        if settings.is_changed() {
+            if let Some(music) = &resource.music {
+                music.set_volume(settings.music_gain() as f64);
+            }
+        }
+    }
 }
- */
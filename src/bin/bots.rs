@@ -0,0 +1,147 @@
+// Headless bot runner for measuring the difficulty curve.
+//
+//     bots [report] [--bot random|dodger] [--games N] [--seed S]
+//          [--difficulty easy|normal|hard|all] [--max-time SECONDS] [--bucket SECONDS]
+//          [--format text|csv|json]
+//
+// Plays N seeded games per difficulty and prints how long the bot survived. `report` prints the
+// full balance report as CSV by default: survival times, what the player died to and how often
+// enemies spawned over time.
+use std::process::exit;
+use switch::analytics::{record_game, to_csv, Report};
+use switch::bot::{DodgerBot, Policy, RandomBot};
+use switch::sim::SimConfig;
+use switch::world::Difficulty;
+
+const USAGE: &str = "usage: bots [report] [--bot random|dodger] [--games N] [--seed S] \
+                     [--difficulty easy|normal|hard|all] [--max-time SECONDS] \
+                     [--bucket SECONDS] [--format text|csv|json]";
+
+#[derive(PartialEq)]
+enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+struct Options {
+    bot: String,
+    games: u64,
+    seed: u64,
+    difficulties: Vec<Difficulty>,
+    max_time: f32,
+    bucket: f32,
+    format: Format,
+}
+
+fn main() {
+    let options = parse(std::env::args().skip(1).collect()).unwrap_or_else(|error| {
+        eprintln!("{}\n{}", error, USAGE);
+        exit(2);
+    });
+    if options.format == Format::Text {
+        println!(
+            "{} bot, {} games per difficulty from seed {}",
+            options.bot, options.games, options.seed
+        );
+    }
+    let mut reports = Vec::new();
+    for difficulty in options.difficulties.iter() {
+        let config = SimConfig {
+            difficulty: *difficulty,
+            ..Default::default()
+        };
+        let max_ticks = (options.max_time * config.tick_rate as f32) as u64;
+        let records: Vec<_> = (0..options.games)
+            .map(|game| {
+                let seed = options.seed.wrapping_add(game);
+                let mut policy = new_policy(&options.bot, seed);
+                record_game(policy.as_mut(), config, seed, max_ticks)
+            })
+            .collect();
+        let report = Report::new(*difficulty, &records, options.bucket, options.max_time);
+        if options.format == Format::Text {
+            print_text(&report);
+        }
+        reports.push(report);
+    }
+    match options.format {
+        Format::Text => {}
+        Format::Csv => print!("{}", to_csv(&reports)),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&reports).unwrap()),
+    }
+}
+
+fn new_policy(bot: &str, seed: u64) -> Box<dyn Policy> {
+    match bot {
+        "random" => Box::new(RandomBot::new(seed)),
+        _ => Box::new(DodgerBot::default()),
+    }
+}
+
+fn parse(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        bot: "dodger".to_string(),
+        games: 100,
+        seed: 0,
+        difficulties: Difficulty::ALL.to_vec(),
+        max_time: 300f32,
+        bucket: 10f32,
+        format: Format::Text,
+    };
+    let mut args = args.into_iter().peekable();
+    if args.peek().map_or(false, |command| command == "report") {
+        args.next();
+        options.format = Format::Csv;
+    }
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        let invalid = || format!("invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--bot" if value == "random" || value == "dodger" => options.bot = value.clone(),
+            "--games" => options.games = value.parse().map_err(|_| invalid())?,
+            "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+            "--max-time" => options.max_time = value.parse().map_err(|_| invalid())?,
+            "--bucket" => {
+                options.bucket = value
+                    .parse()
+                    .ok()
+                    .filter(|bucket| *bucket > 0f32)
+                    .ok_or_else(invalid)?
+            }
+            "--difficulty" if value == "all" => options.difficulties = Difficulty::ALL.to_vec(),
+            "--difficulty" => {
+                options.difficulties = vec![Difficulty::ALL
+                    .iter()
+                    .copied()
+                    .find(|difficulty| format!("{:?}", difficulty).to_lowercase() == value)
+                    .ok_or_else(invalid)?]
+            }
+            "--format" => {
+                options.format = match value.as_str() {
+                    "text" => Format::Text,
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(options)
+}
+
+fn print_text(report: &Report) {
+    println!(
+        "\n{:?}: mean {:.1}s, p10 {:.1}s, median {:.1}s, p90 {:.1}s, best {:.1}s",
+        report.difficulty, report.mean, report.p10, report.median, report.p90, report.best
+    );
+    for bucket in report.survival_curve.iter() {
+        println!("  {:>5.0}s {:>5.1}%", bucket.start, bucket.value * 100f32);
+        if bucket.value == 0f32 {
+            break;
+        }
+    }
+}
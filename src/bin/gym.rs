@@ -0,0 +1,86 @@
+// Reinforcement learning environment over stdin and stdout, one JSON request per line and one
+// JSON response line each.
+//
+//     {"cmd": "reset", "seed": 7}                             -> {"obs": [...]}
+//     {"cmd": "reset", "seed": 7, "config": {...}}            -> {"obs": [...]}
+//     {"cmd": "step", "action": {"press": true, "aim": [x, y]}} -> {"obs", "reward", "done", ...}
+//     {"cmd": "spec"}                                         -> {"observation_size", "config"}
+use serde::{Deserialize, Serialize};
+use std::io::{stdin, stdout, BufRead, Write};
+use switch::gym::{Action, Env, EnvConfig, OBSERVATION_SIZE};
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Reset {
+        seed: u64,
+        #[serde(default)]
+        config: Option<EnvConfig>,
+    },
+    Step {
+        #[serde(default)]
+        action: Action,
+    },
+    Spec,
+}
+
+#[derive(Serialize)]
+struct Reset {
+    obs: Vec<f32>,
+}
+
+#[derive(Serialize)]
+struct Spec {
+    observation_size: usize,
+    config: EnvConfig,
+}
+
+#[derive(Serialize)]
+struct Error {
+    error: String,
+}
+
+fn main() {
+    let mut env = Env::new(EnvConfig::default());
+    let stdin = stdin();
+    let mut stdout = stdout();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Reset { seed, config }) => {
+                if let Some(config) = config {
+                    env.config = config;
+                }
+                serde_json::to_string(&Reset {
+                    obs: env.reset(seed),
+                })
+            }
+            Ok(Request::Step { action }) => match env.step(action) {
+                Some(step) => serde_json::to_string(&step),
+                None => serde_json::to_string(&Error {
+                    error: "reset before stepping".to_string(),
+                }),
+            },
+            Ok(Request::Spec) => serde_json::to_string(&Spec {
+                observation_size: OBSERVATION_SIZE,
+                config: env.config,
+            }),
+            Err(error) => serde_json::to_string(&Error {
+                error: error.to_string(),
+            }),
+        };
+        let response = response.unwrap_or_else(|_| "{}".to_string());
+        if writeln!(stdout, "{}", response)
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// Reference verifier for online leaderboard submissions.
+//
+//     verifier <submission.json>   verify one submission and print the verdict
+//     verifier --serve <address>   run a mock leaderboard server that verifies every submission
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use switch::protocol::{verify, Submission, Verdict, LEADERBOARD_PATH, SUBMIT_PATH};
+
+// Hours of inputs fit in well under this.
+const MAX_BODY_SIZE: usize = 4 * 1024 * 1024;
+// A client that stalls for this long is dropped.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize)]
+struct Entry {
+    name: String,
+    time: f32,
+    score: u32,
+    seed: u64,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [flag, address] if flag == "--serve" => serve(address),
+        [path] => verify_file(path),
+        _ => {
+            eprintln!("usage: verifier <submission.json> | verifier --serve <address>");
+            exit(2);
+        }
+    }
+}
+
+fn verify_file(path: &str) {
+    let submission: Submission = match std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|error| error.to_string()))
+    {
+        Ok(submission) => submission,
+        Err(error) => {
+            eprintln!("could not read {}: {}", path, error);
+            exit(2);
+        }
+    };
+    let verdict = verify(&submission);
+    println!("{}", serde_json::to_string(&verdict).unwrap());
+    if !verdict.accepted {
+        exit(1);
+    }
+}
+
+fn serve(address: &str) {
+    let listener = TcpListener::bind(address).unwrap_or_else(|error| {
+        eprintln!("could not bind {}: {}", address, error);
+        exit(2);
+    });
+    // The bound address, so port 0 shows which port was picked.
+    match listener.local_addr() {
+        Ok(bound) => println!("listening on {}", bound),
+        Err(_) => println!("listening on {}", address),
+    }
+    // Every connection gets its own thread, so a slow client does not hold up the others.
+    let entries: Arc<Mutex<Vec<Entry>>> = Arc::default();
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let entries = Arc::clone(&entries);
+        thread::spawn(move || {
+            if stream.set_read_timeout(Some(IO_TIMEOUT)).is_err()
+                || stream.set_write_timeout(Some(IO_TIMEOUT)).is_err()
+            {
+                return;
+            }
+            let response = match read_request(&mut stream) {
+                Ok(request) => handle(request, &entries),
+                Err(status) => (status, "{}".to_string()),
+            };
+            write_response(&mut stream, response).unwrap_or(());
+        });
+    }
+}
+
+fn handle(request: Request, entries: &Mutex<Vec<Entry>>) -> (u16, String) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("OPTIONS", _) => (204, String::new()),
+        ("POST", SUBMIT_PATH) => {
+            let verdict = match serde_json::from_slice::<Submission>(&request.body) {
+                Ok(submission) => {
+                    let verdict = verify(&submission);
+                    println!(
+                        "{} {:.2}s {}: {}",
+                        submission.name,
+                        submission.claimed_time,
+                        submission.claimed_score,
+                        verdict.reason.as_deref().unwrap_or("accepted")
+                    );
+                    if verdict.accepted {
+                        entries.lock().unwrap().push(Entry {
+                            name: submission.name,
+                            time: verdict.time,
+                            score: verdict.score,
+                            seed: submission.replay.seed,
+                        });
+                    }
+                    verdict
+                }
+                Err(error) => Verdict {
+                    accepted: false,
+                    time: 0f32,
+                    score: 0,
+                    reason: Some(error.to_string()),
+                },
+            };
+            (200, serde_json::to_string(&verdict).unwrap())
+        }
+        ("GET", LEADERBOARD_PATH) => {
+            let entries = entries.lock().unwrap();
+            (200, serde_json::to_string(&*entries).unwrap())
+        }
+        _ => (404, "{}".to_string()),
+    }
+}
+
+// Fails with the status to answer with.
+fn read_request(stream: &mut TcpStream) -> Result<Request, u16> {
+    const BAD_REQUEST: u16 = 400;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|_| BAD_REQUEST)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or(BAD_REQUEST)?.to_string();
+    let path = parts.next().ok_or(BAD_REQUEST)?.to_string();
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).map_err(|_| BAD_REQUEST)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().map_err(|_| BAD_REQUEST)?;
+            }
+        }
+    }
+    if length > MAX_BODY_SIZE {
+        return Err(413);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|_| BAD_REQUEST)?;
+    Ok(Request { method, path, body })
+}
+
+fn write_response(stream: &mut TcpStream, (status, body): (u16, String)) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        413 => "Payload Too Large",
+        _ => "Not Found",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )
+}
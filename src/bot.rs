@@ -0,0 +1,258 @@
+use crate::player::{teams, CoolDown, PlayerMarker, PlayerSlot, Switch};
+use crate::sim::{HeadlessSim, InputQueue, Replay, RunResult, SimClock, SimConfig};
+use crate::ui::GameStartTimer;
+use crate::world::{CharType, ObjectMarker, Velocity, ARENA_SIZE};
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Where the inside of the walls is, measured from the center of the arena.
+const WALL_INSET: f32 = ARENA_SIZE * 0.5f32 - 20f32;
+
+const RANDOM_PRESS_CHANCE: f32 = 0.05f32;
+
+const DODGER_REACTION_TICKS: u64 = 6;
+const DODGER_DANGER_RADIUS: f32 = 160f32;
+const DODGER_WALL_RADIUS: f32 = 80f32;
+const DODGER_AIM_DISTANCE: f32 = 100f32;
+const DODGER_THRUST_DANGER: f32 = 0.3f32;
+
+pub struct ObservedEntity {
+    pub char_type: CharType,
+    pub team: u8,
+    pub location: Vec2,
+    pub velocity: Vec2,
+}
+
+pub struct ObservedPlayer {
+    pub location: Vec2,
+    pub velocity: Vec2,
+    pub team: u8,
+    pub bullet_team: u8,
+    // Whether the next switch press thrusts rather than fires.
+    pub thrust_next: bool,
+    pub ready: bool,
+}
+
+// What a policy gets to see of the world at the start of a tick.
+pub struct Observation {
+    pub tick: u64,
+    pub time: f32,
+    pub player: Option<ObservedPlayer>,
+    pub entities: Vec<ObservedEntity>,
+}
+
+impl Observation {
+    pub fn new(world: &mut World, slot: u8) -> Self {
+        let tick = world.get_resource::<SimClock>().unwrap().tick;
+        let time = world
+            .get_resource::<GameStartTimer>()
+            .unwrap()
+            .current_time
+            .0;
+        let mode = world.get_resource::<SimConfig>().unwrap().mode;
+        let player = world
+            .query_filtered::<(
+                &PlayerSlot,
+                &ObjectMarker,
+                &Transform,
+                &Velocity,
+                &Switch,
+                &CoolDown,
+            ), With<PlayerMarker>>()
+            .iter(world)
+            .find(|(player, ..)| player.0 == slot)
+            .map(
+                |(_, team, transform, velocity, switch, cool_down)| ObservedPlayer {
+                    location: transform.translation.truncate(),
+                    velocity: velocity.0,
+                    team: team.0,
+                    bullet_team: teams(mode, slot).1,
+                    thrust_next: switch.0,
+                    ready: cool_down.0 <= 0f32,
+                },
+            );
+        let mut entities = Vec::new();
+        for (char_type, team, transform, velocity, player) in world
+            .query::<(
+                &CharType,
+                &ObjectMarker,
+                &Transform,
+                Option<&Velocity>,
+                Option<&PlayerSlot>,
+            )>()
+            .iter(world)
+        {
+            if player.map_or(false, |player| player.0 == slot) {
+                continue;
+            }
+            entities.push(ObservedEntity {
+                char_type: *char_type,
+                team: team.0,
+                location: transform.translation.truncate(),
+                velocity: velocity.map_or(Vec2::ZERO, |velocity| velocity.0),
+            });
+        }
+        Observation {
+            tick,
+            time,
+            player,
+            entities,
+        }
+    }
+
+    // Everything that kills the player on contact, nearest first.
+    pub fn threats(&self) -> Vec<&ObservedEntity> {
+        let player = match &self.player {
+            Some(player) => player,
+            None => return Vec::new(),
+        };
+        let mut threats: Vec<&ObservedEntity> = self
+            .entities
+            .iter()
+            .filter(|entity| entity.team != player.team && entity.team != player.bullet_team)
+            .collect();
+        threats.sort_by(|a, b| {
+            let a = a.location.distance_squared(player.location);
+            let b = b.location.distance_squared(player.location);
+            a.partial_cmp(&b).unwrap()
+        });
+        threats
+    }
+}
+
+// Drives one player, returning where to press the switch this tick, if at all.
+pub trait Policy {
+    fn act(&mut self, observation: &Observation) -> Option<Vec2>;
+}
+
+// Presses at random points at random times.
+pub struct RandomBot {
+    rng: StdRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        RandomBot {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Policy for RandomBot {
+    fn act(&mut self, _observation: &Observation) -> Option<Vec2> {
+        if self.rng.gen::<f32>() >= RANDOM_PRESS_CHANCE {
+            return None;
+        }
+        Some(Vec2::new(
+            self.rng.gen_range(-WALL_INSET..WALL_INSET),
+            self.rng.gen_range(-WALL_INSET..WALL_INSET),
+        ))
+    }
+}
+
+// Shoots at the nearest threat, then thrusts away from the threats and walls close by.
+#[derive(Default)]
+pub struct DodgerBot;
+
+// Away from the wall along one axis, the stronger the closer the wall.
+fn wall_push(position: f32) -> f32 {
+    let distance = WALL_INSET - position.abs();
+    if distance < DODGER_WALL_RADIUS {
+        -position.signum() * (1f32 - distance.max(0f32) / DODGER_WALL_RADIUS)
+    } else {
+        0f32
+    }
+}
+
+impl DodgerBot {
+    fn escape(observation: &Observation, player: &ObservedPlayer) -> Vec2 {
+        let mut away = Vec2::ZERO;
+        for threat in observation.threats() {
+            let offset = player.location - threat.location;
+            let distance = offset.length();
+            if distance < DODGER_DANGER_RADIUS && distance > 0f32 {
+                away += offset / distance * (1f32 - distance / DODGER_DANGER_RADIUS);
+            }
+        }
+        away + Vec2::new(wall_push(player.location.x), wall_push(player.location.y))
+    }
+}
+
+impl Policy for DodgerBot {
+    fn act(&mut self, observation: &Observation) -> Option<Vec2> {
+        let player = observation.player.as_ref()?;
+        if !player.ready || observation.tick % DODGER_REACTION_TICKS != 0 {
+            return None;
+        }
+        if player.thrust_next {
+            let away = Self::escape(observation, player);
+            if away.length() < DODGER_THRUST_DANGER {
+                return None;
+            }
+            Some(player.location + away.normalize() * DODGER_AIM_DISTANCE)
+        } else {
+            let target = observation.threats().first()?.location;
+            Some(target)
+        }
+    }
+}
+
+// A seeded run played headlessly by a policy for the first player.
+pub struct BotRun {
+    sim: HeadlessSim,
+}
+
+impl BotRun {
+    pub fn new(config: SimConfig, seed: u64) -> Self {
+        BotRun {
+            sim: HeadlessSim::new(&Replay {
+                config,
+                seed,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn observe(&mut self) -> Observation {
+        Observation::new(self.sim.world(), 0)
+    }
+
+    // Queues the press for the current tick, through the same queue the controls fill.
+    pub fn press(&mut self, aim: Vec2) {
+        let world = self.sim.world();
+        let tick = world.get_resource::<SimClock>().unwrap().tick;
+        world
+            .get_resource_mut::<InputQueue>()
+            .unwrap()
+            .push(tick, 0, aim);
+    }
+
+    pub fn step(&mut self) {
+        let tick = self.sim.world().get_resource::<SimClock>().unwrap().tick;
+        self.sim.advance_to(tick + 1);
+    }
+
+    pub fn sim(&mut self) -> &mut HeadlessSim {
+        &mut self.sim
+    }
+
+    pub fn result(&mut self) -> RunResult {
+        self.sim.result()
+    }
+}
+
+// Plays until the player dies or `max_ticks` have passed.
+pub fn play(policy: &mut dyn Policy, config: SimConfig, seed: u64, max_ticks: u64) -> RunResult {
+    let mut run = BotRun::new(config, seed);
+    loop {
+        let result = run.result();
+        if result.finished || result.ticks >= max_ticks {
+            return result;
+        }
+        if let Some(aim) = policy.act(&run.observe()) {
+            run.press(aim);
+        }
+        run.step();
+    }
+}
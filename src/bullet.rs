@@ -1,12 +1,10 @@
+use crate::collision::Collider;
 use crate::player::PLAYER_SIZE;
-use crate::world::{
-    BounceMarker, CharType, Counter, DefaultSize, Location, MaterialResource, ObjectMarker,
-    Velocity,
-};
+use crate::world::{BounceMarker, CharType, Counter, MaterialResource, ObjectMarker, Velocity};
 use bevy::prelude::*;
 
 const SIZE: f32 = 7.5f32;
-const BULLET_VELOCITY: f32 = 275f32;
+pub const BULLET_VELOCITY: f32 = 275f32;
 const TIME: f32 = 5f32;
 
 #[derive(Bundle)]
@@ -16,30 +14,32 @@ pub struct BulletBundle {
     type_marker: CharType,
     #[bundle]
     sprite: SpriteBundle,
-    size: DefaultSize,
-    location: Location,
+    collider: Collider,
     velocity: Velocity,
     counter: Counter,
 }
 
-pub fn new_bullet(target: Vec2, source: Vec2, material: Handle<ColorMaterial>) -> BulletBundle {
+pub fn new_bullet(
+    target: Vec2,
+    source: Vec2,
+    speed: f32,
+    team: u8,
+    material: Handle<ColorMaterial>,
+) -> BulletBundle {
     let dir = (target - source).normalize();
+    let location = source + PLAYER_SIZE * dir;
     BulletBundle {
         marker: BounceMarker,
-        obj_marker: ObjectMarker(2),
+        obj_marker: ObjectMarker(team),
         type_marker: CharType::Bullet,
         sprite: SpriteBundle {
             sprite: Sprite::new(Vec2::new(SIZE, SIZE)),
-            transform: Transform::from_translation(Vec3::new(source.x, source.y, 0f32)),
+            transform: Transform::from_translation(Vec3::new(location.x, location.y, 0f32)),
             material,
             ..Default::default()
         },
-        size: DefaultSize {
-            width: SIZE,
-            height: SIZE,
-        },
-        location: Location(Vec2::new(source.x, source.y) + PLAYER_SIZE * dir),
+        collider: Collider::circle(SIZE),
         counter: Counter(TIME),
-        velocity: Velocity(dir * BULLET_VELOCITY),
+        velocity: Velocity(dir * speed),
     }
 }
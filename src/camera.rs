@@ -0,0 +1,158 @@
+use crate::particles::ScreenShake;
+use crate::player::PlayerMarker;
+use crate::settings::Settings;
+use crate::world::{CharType, MainCamera, ARENA_SIZE};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::render::camera::{Camera, CameraProjection, OrthographicProjection, ScalingMode};
+use serde::{Deserialize, Serialize};
+
+const FOLLOW_ZOOM: f32 = 1.6f32;
+const FOLLOW_SMOOTHING: f32 = 5f32;
+const ZOOM_SMOOTHING: f32 = 2f32;
+const MAX_ZOOM: f32 = 2f32;
+const THREAT_MARGIN: f32 = 80f32;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum CameraMode {
+    ArenaFit,
+    Follow,
+    DynamicZoom,
+}
+
+impl Default for CameraMode {
+    fn default() -> Self {
+        CameraMode::ArenaFit
+    }
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 3] = [
+        CameraMode::ArenaFit,
+        CameraMode::Follow,
+        CameraMode::DynamicZoom,
+    ];
+
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::ArenaFit => CameraMode::Follow,
+            CameraMode::Follow => CameraMode::DynamicZoom,
+            CameraMode::DynamicZoom => CameraMode::ArenaFit,
+        }
+    }
+}
+
+// Where the camera looks in arena units, and how far it is zoomed in relative to fitting the
+// whole arena on screen.
+pub struct CameraView {
+    pub center: Vec2,
+    pub zoom: f32,
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(CameraView {
+            center: Vec2::ZERO,
+            zoom: 1f32,
+        })
+        .add_system(fit_arena.system())
+        .add_system(update_camera_view.system())
+        .add_system(apply_camera.system());
+    }
+}
+
+// Fixes the shorter side of the window to the arena size so the rest of the window letterboxes
+// around it.
+pub fn fit_arena(
+    windows: Res<Windows>,
+    mut camera: Query<(&mut Camera, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let window = windows.get_primary().unwrap();
+    if let Ok((mut camera, mut projection)) = camera.single_mut() {
+        let portrait = window.width() < window.height();
+        let fits = match projection.scaling_mode {
+            ScalingMode::FixedVertical => !portrait,
+            ScalingMode::FixedHorizontal => portrait,
+            _ => false,
+        };
+        if !fits {
+            projection.scaling_mode = if portrait {
+                ScalingMode::FixedHorizontal
+            } else {
+                ScalingMode::FixedVertical
+            };
+            projection.update(window.width(), window.height());
+            camera.projection_matrix = projection.get_projection_matrix();
+        }
+    }
+}
+
+// Keeps the visible area inside the arena so zooming in never shows what is beyond the walls.
+fn clamp_center(center: Vec2, zoom: f32) -> Vec2 {
+    let limit = (ARENA_SIZE * 0.5f32) * (1f32 - 1f32 / zoom);
+    center.clamp(Vec2::splat(-limit), Vec2::splat(limit))
+}
+
+// Keeps every point in view with a margin around them, zoomed in no further than `max_zoom`.
+fn frame(points: &[Vec2], max_zoom: f32) -> (Vec2, f32) {
+    let (min, max) = points
+        .iter()
+        .fold((points[0], points[0]), |(min, max), point| {
+            (min.min(*point), max.max(*point))
+        });
+    let half_extent = ((max - min) * 0.5f32).max_element() + THREAT_MARGIN;
+    let zoom = (ARENA_SIZE * 0.5f32 / half_extent).max(1f32).min(max_zoom);
+    ((min + max) * 0.5f32, zoom)
+}
+
+pub fn update_camera_view(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    mut view: ResMut<CameraView>,
+    players: Query<&Transform, With<PlayerMarker>>,
+    characters: Query<(&Transform, &CharType)>,
+) {
+    // Every living player stays in view.
+    let mut points: Vec<Vec2> = players
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    let (center, zoom) = match (state.current(), settings.camera_mode) {
+        _ if points.is_empty() => (Vec2::ZERO, 1f32),
+        (GameState::Game, CameraMode::Follow) => frame(&points, FOLLOW_ZOOM),
+        (GameState::Game, CameraMode::DynamicZoom) => {
+            points.extend(
+                characters
+                    .iter()
+                    .filter(|(_, char_type)| **char_type == CharType::Enemy)
+                    .map(|(transform, _)| transform.translation.truncate()),
+            );
+            frame(&points, MAX_ZOOM)
+        }
+        _ => (Vec2::ZERO, 1f32),
+    };
+
+    let delta_seconds = time.delta_seconds();
+    let follow = 1f32 - (-FOLLOW_SMOOTHING * delta_seconds).exp();
+    let zoom_follow = 1f32 - (-ZOOM_SMOOTHING * delta_seconds).exp();
+    view.zoom += (zoom - view.zoom) * zoom_follow;
+    let target = clamp_center(center, view.zoom);
+    view.center += (target - view.center) * follow;
+    view.center = clamp_center(view.center, view.zoom);
+}
+
+pub fn apply_camera(
+    view: Res<CameraView>,
+    shake: Res<ScreenShake>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if let Ok(mut transform) = camera.single_mut() {
+        let position = view.center + shake.offset;
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        transform.scale = Vec3::new(1f32 / view.zoom, 1f32 / view.zoom, 1f32);
+    }
+}
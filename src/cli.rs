@@ -0,0 +1,417 @@
+use crate::menu::GameMode;
+use crate::rng::{random_seed, RunSeed};
+use crate::settings::{LaunchOverrides, Settings};
+use crate::sim::{
+    FixedSeed, HeadlessSim, InputLog, Replay, ReplayRequest, SimConfig, DEFAULT_TICK_RATE,
+};
+use crate::world::Difficulty;
+use crate::GameState;
+use bevy::prelude::*;
+
+pub const USAGE: &str = "usage: switch [--seed N] [--mode classic|daily|versus|coop] \
+                         [--difficulty easy|normal|hard] [--windowed | --fullscreen] \
+                         [--size WIDTHxHEIGHT] [--replay FILE] [--record FILE] [--headless] \
+                         [--tick-rate N]";
+
+// How far a headless run goes at most, an hour of play.
+const HEADLESS_MAX_SECONDS: u64 = 60 * 60;
+
+// Options for launching the game, from the command line natively and from the query string of
+// the page on the web, e.g. `?seed=7&mode=versus&fullscreen`.
+#[derive(Default)]
+pub struct LaunchOptions {
+    pub seed: Option<u64>,
+    pub mode: Option<GameMode>,
+    pub difficulty: Option<Difficulty>,
+    pub fullscreen: Option<bool>,
+    pub size: Option<(f32, f32)>,
+    pub replay: Option<String>,
+    pub record: Option<String>,
+    pub headless: bool,
+    pub tick_rate: Option<u32>,
+}
+
+fn parse_named<T: std::fmt::Debug + Copy>(all: &[T], value: &str) -> Option<T> {
+    all.iter()
+        .copied()
+        .find(|item| format!("{:?}", item).eq_ignore_ascii_case(value))
+}
+
+impl LaunchOptions {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(backend::options())
+    }
+
+    // Takes the options as names with optional values, which both sources come down to.
+    pub fn parse(options: Vec<(String, Option<String>)>) -> Result<Self, String> {
+        let mut launch = LaunchOptions::default();
+        for (name, value) in options {
+            let invalid = || match &value {
+                Some(value) => format!("invalid value for {}: {}", name, value),
+                None => format!("missing value for {}", name),
+            };
+            let text = value.as_deref().unwrap_or("");
+            match name.as_str() {
+                "seed" => launch.seed = Some(text.parse().map_err(|_| invalid())?),
+                "mode" => {
+                    launch.mode = Some(parse_named(&GameMode::ALL, text).ok_or_else(invalid)?)
+                }
+                "difficulty" => {
+                    launch.difficulty =
+                        Some(parse_named(&Difficulty::ALL, text).ok_or_else(invalid)?)
+                }
+                "windowed" => launch.fullscreen = Some(false),
+                "fullscreen" => launch.fullscreen = Some(true),
+                "size" => {
+                    let mut parts = text.split('x').map(str::parse::<f32>);
+                    launch.size = match (parts.next(), parts.next(), parts.next()) {
+                        (Some(Ok(width)), Some(Ok(height)), None)
+                            if width > 0f32 && height > 0f32 =>
+                        {
+                            Some((width, height))
+                        }
+                        _ => return Err(invalid()),
+                    }
+                }
+                "replay" if value.is_some() => launch.replay = value.clone(),
+                "record" if value.is_some() => launch.record = value.clone(),
+                "headless" => launch.headless = true,
+                "tick-rate" => {
+                    launch.tick_rate = Some(
+                        text.parse()
+                            .ok()
+                            .filter(|rate| *rate > 0)
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                "replay" | "record" => return Err(invalid()),
+                _ => return Err(format!("unknown option {}", name)),
+            }
+        }
+        Ok(launch)
+    }
+
+    fn load_replay(&self) -> Result<Option<Replay>, String> {
+        match &self.replay {
+            Some(path) => backend::read_file(path)
+                .and_then(|contents| {
+                    serde_json::from_str(&contents).map_err(|error| error.to_string())
+                })
+                .map(Some)
+                .map_err(|error| format!("could not read replay {}: {}", path, error)),
+            None => Ok(None),
+        }
+    }
+
+    fn apply_settings(&self, settings: &mut Settings) -> LaunchOverrides {
+        let mut overrides = LaunchOverrides::default();
+        if let Some(mode) = self.mode {
+            overrides.game_mode = Some((mode, settings.game_mode));
+            settings.game_mode = mode;
+        }
+        if let Some(difficulty) = self.difficulty {
+            overrides.difficulty = Some((difficulty, settings.difficulty));
+            settings.difficulty = difficulty;
+        }
+        if let Some(fullscreen) = self.fullscreen {
+            overrides.fullscreen = Some((fullscreen, settings.fullscreen));
+            settings.fullscreen = fullscreen;
+        }
+        overrides
+    }
+
+    // Must be inserted before the window is created.
+    pub fn window(&self) -> Option<WindowDescriptor> {
+        self.size.map(|(width, height)| WindowDescriptor {
+            width,
+            height,
+            title: "switch".to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+// Applies the options to the game app, after `SimPlugin` has added its resources.
+pub struct LaunchPlugin {
+    options: LaunchOptions,
+    replay: Option<Replay>,
+}
+
+impl LaunchPlugin {
+    pub fn new(options: LaunchOptions) -> Result<Self, String> {
+        let replay = options.load_replay()?;
+        Ok(LaunchPlugin { options, replay })
+    }
+}
+
+// Where to write the recording of every run when it ends.
+pub struct RecordPath(pub String);
+
+impl Plugin for LaunchPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let options = &self.options;
+        let world = app.world_mut();
+        let overrides = match world.get_resource_mut::<Settings>() {
+            Some(mut settings) => options.apply_settings(&mut settings),
+            None => LaunchOverrides::default(),
+        };
+        world.insert_resource(overrides);
+        if let Some(tick_rate) = options.tick_rate {
+            world.get_resource_mut::<SimConfig>().unwrap().tick_rate = tick_rate;
+        }
+        world.insert_resource(FixedSeed(options.seed));
+        if let Some(replay) = &self.replay {
+            world.insert_resource(ReplayRequest(Some(replay.clone())));
+            app.add_system_set(
+                SystemSet::on_enter(GameState::Menu).with_system(start_replay.system()),
+            );
+        }
+        if let Some(path) = &options.record {
+            app.insert_resource(RecordPath(path.clone()))
+                .add_system_set(
+                    SystemSet::on_exit(GameState::Game).with_system(write_recording.system()),
+                );
+        }
+    }
+}
+
+// Goes straight from the menu into the replay the first time.
+fn start_replay(request: Res<ReplayRequest>, mut state: ResMut<State<GameState>>) {
+    if request.0.is_some() {
+        state.set(GameState::Game).unwrap_or(());
+    }
+}
+
+fn write_recording(
+    path: Res<RecordPath>,
+    config: Res<SimConfig>,
+    seed: Res<RunSeed>,
+    log: Res<InputLog>,
+) {
+    let replay = Replay {
+        config: *config,
+        seed: seed.0,
+        inputs: log.0.clone(),
+    };
+    if let Err(error) = write_replay(&path.0, &replay) {
+        error!("could not write the recording to {}: {}", path.0, error);
+    }
+}
+
+fn write_replay(path: &str, replay: &Replay) -> Result<(), String> {
+    let contents = serde_json::to_string(replay).map_err(|error| error.to_string())?;
+    backend::write_file(path, &contents)
+}
+
+// Plays the replay, or a run without input, without a window and prints how it went.
+pub fn run_headless(options: &LaunchOptions) -> Result<(), String> {
+    let replay = match options.load_replay()? {
+        Some(replay) => replay,
+        None => {
+            let mut settings = Settings::load();
+            options.apply_settings(&mut settings);
+            Replay {
+                config: SimConfig::new(&settings, options.tick_rate.unwrap_or(DEFAULT_TICK_RATE)),
+                seed: options.seed.unwrap_or_else(random_seed),
+                ..Default::default()
+            }
+        }
+    };
+    let mut sim = HeadlessSim::new(&replay);
+    sim.advance_to(HEADLESS_MAX_SECONDS * replay.config.tick_rate as u64);
+    let result = sim.result();
+    println!(
+        "seed {} ticks {} time {:.2} score {}{}",
+        replay.seed,
+        result.ticks,
+        result.time,
+        result.score,
+        if result.finished { "" } else { " (cut off)" }
+    );
+    if let Some(path) = &options.record {
+        let world = sim.world();
+        let recording = Replay {
+            inputs: world.get_resource::<InputLog>().unwrap().0.clone(),
+            ..replay
+        };
+        write_replay(path, &recording)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    pub fn options() -> Vec<(String, Option<String>)> {
+        split_args(std::env::args().skip(1))
+    }
+
+    // `--name value` and `--flag`, a value is whatever follows that does not start with `--`.
+    pub fn split_args(args: impl IntoIterator<Item = String>) -> Vec<(String, Option<String>)> {
+        let mut options = Vec::new();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            let name = arg.trim_start_matches("--").to_string();
+            let value = match args.peek() {
+                Some(next) if !next.starts_with("--") => args.next(),
+                _ => None,
+            };
+            options.push((name, value));
+        }
+        options
+    }
+
+    pub fn read_file(path: &str) -> Result<String, String> {
+        std::fs::read_to_string(path).map_err(|error| error.to_string())
+    }
+
+    pub fn write_file(path: &str, contents: &str) -> Result<(), String> {
+        std::fs::write(path, contents).map_err(|error| error.to_string())
+    }
+}
+
+// Files on the web are the game's local storage, under the given name.
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use crate::storage;
+
+    // `?name=value&flag`, values are not percent decoded.
+    pub fn options() -> Vec<(String, Option<String>)> {
+        let search = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+        search
+            .trim_start_matches('?')
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                let name = parts.next().unwrap_or("").to_string();
+                (name, parts.next().map(str::to_string))
+            })
+            .collect()
+    }
+
+    pub fn read_file(path: &str) -> Result<String, String> {
+        storage::load::<serde_json::Value>(path)
+            .map(|value| value.to_string())
+            .ok_or_else(|| "not in local storage".to_string())
+    }
+
+    pub fn write_file(path: &str, contents: &str) -> Result<(), String> {
+        let value: serde_json::Value =
+            serde_json::from_str(contents).map_err(|error| error.to_string())?;
+        storage::save(path, &value);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<LaunchOptions, String> {
+        LaunchOptions::parse(backend::split_args(args.iter().map(|arg| arg.to_string())))
+    }
+
+    fn error(args: &[&str]) -> String {
+        parse(args).err().expect("the options should be rejected")
+    }
+
+    #[test]
+    fn splits_flags_and_values() {
+        let options = backend::split_args(
+            ["--headless", "--seed", "42", "--replay", "--windowed"]
+                .iter()
+                .map(|arg| arg.to_string()),
+        );
+        assert_eq!(
+            options,
+            vec![
+                ("headless".to_string(), None),
+                ("seed".to_string(), Some("42".to_string())),
+                ("replay".to_string(), None),
+                ("windowed".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let options = parse(&[
+            "--seed",
+            "42",
+            "--mode",
+            "COOP",
+            "--difficulty",
+            "hard",
+            "--fullscreen",
+            "--size",
+            "1280x720",
+            "--replay",
+            "run.json",
+            "--record",
+            "out.json",
+            "--headless",
+            "--tick-rate",
+            "120",
+        ])
+        .unwrap();
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.mode, Some(GameMode::Coop));
+        assert_eq!(options.difficulty, Some(Difficulty::Hard));
+        assert_eq!(options.fullscreen, Some(true));
+        assert_eq!(options.size, Some((1280f32, 720f32)));
+        assert_eq!(options.replay.as_deref(), Some("run.json"));
+        assert_eq!(options.record.as_deref(), Some("out.json"));
+        assert!(options.headless);
+        assert_eq!(options.tick_rate, Some(120));
+    }
+
+    #[test]
+    fn later_options_win() {
+        let options = parse(&["--fullscreen", "--windowed"]).unwrap();
+        assert_eq!(options.fullscreen, Some(false));
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert_eq!(error(&["--speed", "2"]), "unknown option speed");
+        assert_eq!(
+            error(&["--mode", "arcade"]),
+            "invalid value for mode: arcade"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_sizes() {
+        assert_eq!(error(&["--size", "0x5"]), "invalid value for size: 0x5");
+        assert_eq!(error(&["--size", "10x"]), "invalid value for size: 10x");
+        assert_eq!(
+            error(&["--size", "10x5x2"]),
+            "invalid value for size: 10x5x2"
+        );
+        assert_eq!(error(&["--size"]), "missing value for size");
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        assert_eq!(error(&["--replay"]), "missing value for replay");
+        assert_eq!(
+            error(&["--record", "--headless"]),
+            "missing value for record"
+        );
+        assert_eq!(error(&["--seed"]), "missing value for seed");
+    }
+
+    #[test]
+    fn rejects_bad_tick_rates() {
+        assert_eq!(
+            error(&["--tick-rate", "0"]),
+            "invalid value for tick-rate: 0"
+        );
+        assert_eq!(
+            error(&["--tick-rate", "-5"]),
+            "invalid value for tick-rate: -5"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use bevy::prelude::*;
+
+#[derive(Debug, Copy, Clone)]
+pub enum Collider {
+    Circle { radius: f32 },
+    Aabb { half_extents: Vec2 },
+    Obb { half_extents: Vec2, angle: f32 },
+}
+
+impl Collider {
+    pub fn circle(diameter: f32) -> Self {
+        Collider::Circle {
+            radius: diameter * 0.5f32,
+        }
+    }
+
+    pub fn aabb(width: f32, height: f32) -> Self {
+        Collider::Aabb {
+            half_extents: Vec2::new(width, height) * 0.5f32,
+        }
+    }
+
+    pub fn obb(width: f32, height: f32, angle: f32) -> Self {
+        Collider::Obb {
+            half_extents: Vec2::new(width, height) * 0.5f32,
+            angle,
+        }
+    }
+}
+
+// `normal` points from the first shape towards the second.
+#[derive(Debug, Copy, Clone)]
+pub struct Contact {
+    pub normal: Vec2,
+    pub depth: f32,
+}
+
+impl Contact {
+    fn flipped(self) -> Self {
+        Contact {
+            normal: -self.normal,
+            depth: self.depth,
+        }
+    }
+}
+
+enum Primitive {
+    Circle(f32),
+    Box(Vec2, f32),
+}
+
+fn primitive(collider: &Collider) -> Primitive {
+    match *collider {
+        Collider::Circle { radius } => Primitive::Circle(radius),
+        Collider::Aabb { half_extents } => Primitive::Box(half_extents, 0f32),
+        Collider::Obb {
+            half_extents,
+            angle,
+        } => Primitive::Box(half_extents, angle),
+    }
+}
+
+pub fn rotate(v: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+}
+
+pub fn collide(a: &Collider, a_pos: Vec2, b: &Collider, b_pos: Vec2) -> Option<Contact> {
+    match (primitive(a), primitive(b)) {
+        (Primitive::Circle(ra), Primitive::Circle(rb)) => circle_circle(a_pos, ra, b_pos, rb),
+        (Primitive::Box(he, angle), Primitive::Circle(r)) => box_circle(a_pos, he, angle, b_pos, r),
+        (Primitive::Circle(r), Primitive::Box(he, angle)) => {
+            box_circle(b_pos, he, angle, a_pos, r).map(Contact::flipped)
+        }
+        (Primitive::Box(he_a, angle_a), Primitive::Box(he_b, angle_b)) => {
+            box_box(a_pos, he_a, angle_a, b_pos, he_b, angle_b)
+        }
+    }
+}
+
+fn circle_circle(a_pos: Vec2, ra: f32, b_pos: Vec2, rb: f32) -> Option<Contact> {
+    let offset = b_pos - a_pos;
+    let distance = offset.length();
+    if distance >= ra + rb {
+        return None;
+    }
+    let normal = if distance > f32::EPSILON {
+        offset / distance
+    } else {
+        Vec2::X
+    };
+    Some(Contact {
+        normal,
+        depth: ra + rb - distance,
+    })
+}
+
+fn box_circle(
+    box_pos: Vec2,
+    half_extents: Vec2,
+    angle: f32,
+    circle_pos: Vec2,
+    radius: f32,
+) -> Option<Contact> {
+    let local = rotate(circle_pos - box_pos, -angle);
+    let clamped = local.max(-half_extents).min(half_extents);
+    let (normal, depth) = if clamped == local {
+        // The circle's center is inside the box, push out along the shallowest face.
+        let x_depth = half_extents.x - local.x.abs();
+        let y_depth = half_extents.y - local.y.abs();
+        if x_depth < y_depth {
+            (Vec2::new(local.x.signum(), 0f32), x_depth + radius)
+        } else {
+            (Vec2::new(0f32, local.y.signum()), y_depth + radius)
+        }
+    } else {
+        let offset = local - clamped;
+        let distance = offset.length();
+        if distance >= radius {
+            return None;
+        }
+        (offset / distance, radius - distance)
+    };
+    Some(Contact {
+        normal: rotate(normal, angle),
+        depth,
+    })
+}
+
+fn box_box(
+    a_pos: Vec2,
+    he_a: Vec2,
+    angle_a: f32,
+    b_pos: Vec2,
+    he_b: Vec2,
+    angle_b: f32,
+) -> Option<Contact> {
+    let axes_a = [rotate(Vec2::X, angle_a), rotate(Vec2::Y, angle_a)];
+    let axes_b = [rotate(Vec2::X, angle_b), rotate(Vec2::Y, angle_b)];
+    let offset = b_pos - a_pos;
+    let mut best: Option<Contact> = None;
+    for axis in axes_a.iter().chain(axes_b.iter()) {
+        let extent_a = he_a.x * axes_a[0].dot(*axis).abs() + he_a.y * axes_a[1].dot(*axis).abs();
+        let extent_b = he_b.x * axes_b[0].dot(*axis).abs() + he_b.y * axes_b[1].dot(*axis).abs();
+        let distance = offset.dot(*axis);
+        let depth = extent_a + extent_b - distance.abs();
+        if depth <= 0f32 {
+            return None;
+        }
+        if best.map_or(true, |contact| depth < contact.depth) {
+            let normal = if distance < 0f32 { -*axis } else { *axis };
+            best = Some(Contact { normal, depth });
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4, SQRT_2};
+
+    const TOLERANCE: f32 = 1e-4;
+
+    fn assert_contact(contact: Option<Contact>, normal: Vec2, depth: f32) {
+        let contact = contact.expect("shapes should overlap");
+        assert!(
+            (contact.normal - normal).length() < TOLERANCE,
+            "normal {:?}, expected {:?}",
+            contact.normal,
+            normal
+        );
+        assert!(
+            (contact.depth - depth).abs() < TOLERANCE,
+            "depth {}, expected {}",
+            contact.depth,
+            depth
+        );
+    }
+
+    #[test]
+    fn circles() {
+        let circle = Collider::circle(2f32);
+        let contact = collide(&circle, Vec2::ZERO, &circle, Vec2::new(1.5f32, 0f32));
+        assert_contact(contact, Vec2::X, 0.5f32);
+        let contact = collide(&circle, Vec2::new(1.5f32, 0f32), &circle, Vec2::ZERO);
+        assert_contact(contact, -Vec2::X, 0.5f32);
+        let contact = collide(&circle, Vec2::ZERO, &circle, Vec2::new(0f32, -1f32));
+        assert_contact(contact, -Vec2::Y, 1f32);
+    }
+
+    #[test]
+    fn circles_touching_or_apart() {
+        let circle = Collider::circle(2f32);
+        assert!(collide(&circle, Vec2::ZERO, &circle, Vec2::new(2f32, 0f32)).is_none());
+        assert!(collide(&circle, Vec2::ZERO, &circle, Vec2::new(3f32, 1f32)).is_none());
+    }
+
+    #[test]
+    fn box_and_circle() {
+        let wall = Collider::aabb(4f32, 2f32);
+        let circle = Collider::circle(2f32);
+        let contact = collide(&wall, Vec2::ZERO, &circle, Vec2::new(2.5f32, 0f32));
+        assert_contact(contact, Vec2::X, 0.5f32);
+        let contact = collide(&circle, Vec2::new(2.5f32, 0f32), &wall, Vec2::ZERO);
+        assert_contact(contact, -Vec2::X, 0.5f32);
+        let contact = collide(&wall, Vec2::ZERO, &circle, Vec2::new(2.5f32, 1.5f32));
+        assert_contact(
+            contact,
+            Vec2::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+            1f32 - FRAC_1_SQRT_2,
+        );
+    }
+
+    #[test]
+    fn circle_inside_box() {
+        let wall = Collider::aabb(4f32, 2f32);
+        let circle = Collider::circle(2f32);
+        let contact = collide(&wall, Vec2::ZERO, &circle, Vec2::new(1.5f32, 0f32));
+        assert_contact(contact, Vec2::X, 1.5f32);
+        let contact = collide(&wall, Vec2::ZERO, &circle, Vec2::new(0f32, -0.5f32));
+        assert_contact(contact, -Vec2::Y, 1.5f32);
+    }
+
+    #[test]
+    fn box_and_circle_touching_or_apart() {
+        let wall = Collider::aabb(4f32, 2f32);
+        let circle = Collider::circle(2f32);
+        assert!(collide(&wall, Vec2::ZERO, &circle, Vec2::new(3f32, 0f32)).is_none());
+        assert!(collide(&circle, Vec2::new(0f32, 2f32), &wall, Vec2::ZERO).is_none());
+        assert!(collide(&wall, Vec2::ZERO, &circle, Vec2::new(2.8f32, 1.8f32)).is_none());
+    }
+
+    #[test]
+    fn rotated_box_and_circle() {
+        // Turned a quarter, the box is 2 wide and 4 tall.
+        let wall = Collider::obb(4f32, 2f32, FRAC_PI_2);
+        let circle = Collider::circle(2f32);
+        let contact = collide(&wall, Vec2::ZERO, &circle, Vec2::new(0f32, 2.5f32));
+        assert_contact(contact, Vec2::Y, 0.5f32);
+        assert!(collide(&wall, Vec2::ZERO, &circle, Vec2::new(2.5f32, 0f32)).is_none());
+    }
+
+    #[test]
+    fn boxes() {
+        let square = Collider::aabb(2f32, 2f32);
+        let contact = collide(&square, Vec2::ZERO, &square, Vec2::new(1.5f32, 0.5f32));
+        assert_contact(contact, Vec2::X, 0.5f32);
+        let contact = collide(&square, Vec2::new(1.5f32, 0.5f32), &square, Vec2::ZERO);
+        assert_contact(contact, -Vec2::X, 0.5f32);
+        let contact = collide(&square, Vec2::ZERO, &square, Vec2::new(0.25f32, -1.75f32));
+        assert_contact(contact, -Vec2::Y, 0.25f32);
+    }
+
+    #[test]
+    fn boxes_touching_or_apart() {
+        let square = Collider::aabb(2f32, 2f32);
+        assert!(collide(&square, Vec2::ZERO, &square, Vec2::new(2f32, 0f32)).is_none());
+        assert!(collide(&square, Vec2::ZERO, &square, Vec2::new(3f32, 3f32)).is_none());
+    }
+
+    #[test]
+    fn rotated_boxes() {
+        let square = Collider::aabb(2f32, 2f32);
+        // Turned an eighth, the diamond reaches `SQRT_2` along x.
+        let diamond = Collider::obb(2f32, 2f32, FRAC_PI_4);
+        let contact = collide(&square, Vec2::ZERO, &diamond, Vec2::new(2.2f32, 0f32));
+        assert_contact(contact, Vec2::X, 1f32 + SQRT_2 - 2.2f32);
+        let contact = collide(&diamond, Vec2::new(2.2f32, 0f32), &square, Vec2::ZERO);
+        assert_contact(contact, -Vec2::X, 1f32 + SQRT_2 - 2.2f32);
+        assert!(collide(&square, Vec2::ZERO, &diamond, Vec2::new(2.5f32, 0f32)).is_none());
+        // Next to a corner of the square, the diamond's own face separates them.
+        assert!(collide(&square, Vec2::ZERO, &diamond, Vec2::new(2f32, 2f32)).is_none());
+    }
+}
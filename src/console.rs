@@ -0,0 +1,284 @@
+use crate::palette::{ActivePalette, TextColorRole};
+use crate::player::PlayerMarker;
+use crate::sim::{Cheated, TimeScale};
+use crate::ui::{text_bundle, Fonts};
+use crate::world::{CharType, EnemyKind, Invulnerable, MouseLoc, SpawnSystem};
+use crate::GameState;
+use bevy::app::Events;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+const OUTPUT_LINES: usize = 6;
+const INPUT_LENGTH: usize = 64;
+const FONT_SIZE: f32 = 20f32;
+const MARGIN: f32 = 8f32;
+
+const HELP: &str = "spawn bouncer|mine, god, time <seconds>, spawning, slow [factor], clear";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    SpawnBouncingEnemy,
+    SpawnHomingMine,
+    GodMode,
+    SetSpawnTime(f32),
+    PauseSpawning,
+    SlowTime(f32),
+    ClearEnemies,
+    Help,
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| {
+            word.parse::<f32>()
+                .ok()
+                .filter(|number| number.is_finite() && *number >= 0f32)
+                .ok_or_else(|| format!("not a number: {}", word))
+        };
+        match words.as_slice() {
+            ["spawn", "bouncer"] => Ok(ConsoleCommand::SpawnBouncingEnemy),
+            ["spawn", "mine"] => Ok(ConsoleCommand::SpawnHomingMine),
+            ["god"] => Ok(ConsoleCommand::GodMode),
+            ["time", seconds] => number(seconds).map(ConsoleCommand::SetSpawnTime),
+            ["spawning"] => Ok(ConsoleCommand::PauseSpawning),
+            ["slow"] => Ok(ConsoleCommand::SlowTime(1f32)),
+            ["slow", factor] => number(factor)
+                .and_then(|factor| {
+                    Some(factor)
+                        .filter(|factor| *factor > 0f32)
+                        .ok_or_else(|| "the factor must be above 0".to_string())
+                })
+                .map(ConsoleCommand::SlowTime),
+            ["clear"] => Ok(ConsoleCommand::ClearEnemies),
+            ["help"] => Ok(ConsoleCommand::Help),
+            _ => Err(format!("unknown command: {}", line.trim())),
+        }
+    }
+}
+
+// The cheats stay on across runs until they are toggled off again.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub output: Vec<String>,
+    pub god_mode: bool,
+    pub spawning_paused: bool,
+}
+
+impl Console {
+    fn print(&mut self, line: String) {
+        self.output.push(line);
+        let extra = self.output.len().saturating_sub(OUTPUT_LINES);
+        self.output.drain(..extra);
+    }
+}
+
+pub struct ConsoleText;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Console>()
+            .add_event::<ConsoleCommand>()
+            // Before anything else reads the keyboard, so it can take the keys while open.
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                type_command.system().after(InputSystem),
+            )
+            .add_system(run_commands.system())
+            .add_system(apply_cheats.system())
+            .add_system(update_console_text.system());
+    }
+}
+
+// While open the console takes every key and character, so they do not reach the controls, the
+// menus or the name entry.
+pub fn type_command(
+    mut characters: ResMut<Events<ReceivedCharacter>>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut console: ResMut<Console>,
+    mut commands: EventWriter<ConsoleCommand>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+        characters.drain().for_each(drop);
+        keys.reset(TOGGLE_KEY);
+        return;
+    }
+    if !console.open {
+        return;
+    }
+    for character in characters.drain() {
+        let valid = !character.char.is_control() && character.char != '`';
+        if valid && console.input.chars().count() < INPUT_LENGTH {
+            console.input.push(character.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        if !line.trim().is_empty() {
+            console.print(format!("> {}", line));
+            match ConsoleCommand::parse(&line) {
+                Ok(command) => commands.send(command),
+                Err(error) => console.print(error),
+            }
+        }
+    }
+    let taken: Vec<KeyCode> = keys
+        .get_pressed()
+        .chain(keys.get_just_released())
+        .copied()
+        .collect();
+    for key in taken {
+        keys.reset(key);
+    }
+}
+
+pub fn run_commands(
+    mut commands: Commands,
+    mut events: EventReader<ConsoleCommand>,
+    mut console: ResMut<Console>,
+    state: Res<State<GameState>>,
+    mouse: Res<MouseLoc>,
+    mut time_scale: ResMut<TimeScale>,
+    mut cheated: ResMut<Cheated>,
+    mut spawn_system: Option<ResMut<SpawnSystem>>,
+    enemies: Query<(Entity, &CharType)>,
+) {
+    for command in events.iter() {
+        let in_run = *state.current() == GameState::Game;
+        if in_run && *command != ConsoleCommand::Help {
+            cheated.0 = true;
+        }
+        let reply = match *command {
+            // Made by the spawner on the next tick, the same way as its own spawns.
+            ConsoleCommand::SpawnBouncingEnemy | ConsoleCommand::SpawnHomingMine => {
+                match spawn_system.as_mut() {
+                    Some(spawn_system) if in_run => {
+                        if *command == ConsoleCommand::SpawnBouncingEnemy {
+                            spawn_system.request(EnemyKind::Bouncing, mouse.location);
+                            "spawned a bouncing enemy".to_string()
+                        } else {
+                            spawn_system.request(EnemyKind::Mine, mouse.location);
+                            "spawned a homing mine".to_string()
+                        }
+                    }
+                    _ => "only during a run".to_string(),
+                }
+            }
+            ConsoleCommand::GodMode => {
+                console.god_mode = !console.god_mode;
+                format!("god mode {}", on_off(console.god_mode))
+            }
+            ConsoleCommand::SetSpawnTime(time) => match spawn_system.as_mut() {
+                Some(spawn_system) if in_run => {
+                    spawn_system.set_time(time);
+                    format!("spawner at {:.1}s", spawn_system.time())
+                }
+                _ => "only during a run".to_string(),
+            },
+            ConsoleCommand::PauseSpawning => {
+                console.spawning_paused = !console.spawning_paused;
+                format!("spawning {}", on_off(!console.spawning_paused))
+            }
+            ConsoleCommand::SlowTime(factor) => {
+                time_scale.0 = factor;
+                format!("time at {}x", factor)
+            }
+            ConsoleCommand::ClearEnemies => {
+                let mut cleared = 0;
+                for (entity, char_type) in enemies.iter() {
+                    if *char_type == CharType::Enemy {
+                        commands.entity(entity).despawn_recursive();
+                        cleared += 1;
+                    }
+                }
+                format!("cleared {} enemies", cleared)
+            }
+            ConsoleCommand::Help => HELP.to_string(),
+        };
+        console.print(reply);
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+// Players and the spawner are new every run, so the cheats are applied to them every frame. A run
+// with any of them on is cheated, even when they were turned on before it.
+pub fn apply_cheats(
+    mut commands: Commands,
+    console: Res<Console>,
+    state: Res<State<GameState>>,
+    time_scale: Res<TimeScale>,
+    mut cheated: ResMut<Cheated>,
+    spawn_system: Option<ResMut<SpawnSystem>>,
+    mortal: Query<Entity, (With<PlayerMarker>, Without<Invulnerable>)>,
+    invulnerable: Query<Entity, (With<PlayerMarker>, With<Invulnerable>)>,
+) {
+    let active = console.god_mode || console.spawning_paused || time_scale.0 != 1f32;
+    if active && *state.current() == GameState::Game && !cheated.0 {
+        cheated.0 = true;
+    }
+    if console.god_mode {
+        for entity in mortal.iter() {
+            commands.entity(entity).insert(Invulnerable);
+        }
+    } else {
+        for entity in invulnerable.iter() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+    if let Some(mut spawn_system) = spawn_system {
+        if spawn_system.paused != console.spawning_paused {
+            spawn_system.paused = console.spawning_paused;
+        }
+    }
+}
+
+pub fn update_console_text(
+    mut commands: Commands,
+    console: Res<Console>,
+    fonts: Option<Res<Fonts>>,
+    palette: Res<ActivePalette>,
+    mut text: Query<(Entity, &mut Text), With<ConsoleText>>,
+) {
+    if !console.open {
+        for (entity, _) in text.iter_mut() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    if !console.is_changed() && text.iter_mut().next().is_some() {
+        return;
+    }
+    let mut value = console.output.join("\n");
+    value.push_str(&format!("\n> {}_", console.input));
+    if let Ok((_, mut text)) = text.single_mut() {
+        text.sections[0].value = value;
+    } else if let Some(fonts) = fonts {
+        let mut line = text_bundle(value, &fonts, FONT_SIZE, palette.palette.text, 0f32);
+        line.style.position_type = PositionType::Absolute;
+        line.style.position = Rect {
+            bottom: Val::Px(MARGIN),
+            left: Val::Px(MARGIN),
+            ..Default::default()
+        };
+        commands
+            .spawn_bundle(line)
+            .insert(TextColorRole::Text)
+            .insert(ConsoleText);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::ghost::new_ghost;
+use crate::player::{new_player_bundle, PlayerMarker, PlayerSlot, PLAYER_SIZE};
+use crate::sim::{SimClock, SimConfig};
+use crate::world::MaterialResource;
+use bevy::prelude::*;
+
+const REVIVE_DISTANCE: f32 = 40f32;
+const REVIVE_TIME: f32 = 2f32;
+
+// Where a fallen co-op player can be revived from, and how long a teammate has stood next to it.
+pub struct Fallen {
+    pub slot: u8,
+    pub progress: f32,
+}
+
+// Where each player was at the start of the tick, so a player that died has somewhere to fall.
+#[derive(Default)]
+pub struct LastPositions(pub Vec<Option<Vec2>>);
+
+pub fn revive_players(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    config: Res<SimConfig>,
+    materials: Res<MaterialResource>,
+    mut last_positions: ResMut<LastPositions>,
+    players: Query<(&PlayerSlot, &Transform), With<PlayerMarker>>,
+    mut fallen: Query<(Entity, &mut Fallen, &Transform)>,
+) {
+    if !config.revive {
+        return;
+    }
+    last_positions.0.resize(config.players as usize, None);
+    for slot in 0..config.players {
+        let alive = players.iter().find(|(player, _)| player.0 == slot);
+        let last = &mut last_positions.0[slot as usize];
+        match (alive, *last) {
+            (Some((_, transform)), _) => *last = Some(transform.translation.truncate()),
+            (None, Some(location)) => {
+                commands
+                    .spawn_bundle(new_ghost(
+                        materials.ghost_material.clone(),
+                        Vec2::splat(PLAYER_SIZE),
+                        location,
+                    ))
+                    .insert(Fallen {
+                        slot,
+                        progress: 0f32,
+                    });
+                *last = None;
+            }
+            (None, None) => {}
+        }
+    }
+    for (entity, mut fallen, transform) in fallen.iter_mut() {
+        let location = transform.translation.truncate();
+        let helped = players.iter().any(|(_, player)| {
+            player.translation.truncate().distance_squared(location)
+                < REVIVE_DISTANCE * REVIVE_DISTANCE
+        });
+        fallen.progress = if helped {
+            fallen.progress + clock.delta
+        } else {
+            0f32
+        };
+        if fallen.progress >= REVIVE_TIME {
+            commands.entity(entity).despawn_recursive();
+            commands.spawn_bundle(new_player_bundle(
+                config.mode,
+                fallen.slot,
+                location,
+                materials.player(fallen.slot),
+            ));
+        }
+    }
+}
+
+pub fn reset_revives(
+    mut commands: Commands,
+    mut last_positions: ResMut<LastPositions>,
+    fallen: Query<Entity, With<Fallen>>,
+) {
+    last_positions.0.clear();
+    for entity in fallen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
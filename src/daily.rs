@@ -0,0 +1,296 @@
+use crate::date::Date;
+use crate::menu::GameMode;
+use crate::rng::{GameRng, RunSeed};
+use crate::sim::{Playback, SimConfig, START_RUN};
+use crate::stats::Score;
+use crate::storage;
+use crate::ui::GameStartTimer;
+use crate::GameState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const DAILY_KEY: &str = "daily";
+pub const BEGIN_DAILY_RUN: &str = "begin_daily_run";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Modifier {
+    MinesOnly,
+    DoubleBulletSpeed,
+    NoThrustDecay,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 3] = [
+        Modifier::MinesOnly,
+        Modifier::DoubleBulletSpeed,
+        Modifier::NoThrustDecay,
+    ];
+
+    pub fn for_date(date: Date) -> Self {
+        Modifier::ALL[date.to_days().rem_euclid(Modifier::ALL.len() as i64) as usize]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Modifier::MinesOnly => "Mines only",
+            Modifier::DoubleBulletSpeed => "Double bullet speed",
+            Modifier::NoThrustDecay => "No thrust decay",
+        }
+    }
+}
+
+// SplitMix64 of the day number, so consecutive days get unrelated seeds.
+pub fn daily_seed(date: Date) -> u64 {
+    let mut z = (date.to_days() as u64).wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub date: Date,
+    pub modifier: Modifier,
+    pub time: f32,
+    pub score: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyHistory {
+    pub results: Vec<DailyResult>,
+}
+
+impl DailyHistory {
+    pub fn load() -> Self {
+        storage::load(DAILY_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(DAILY_KEY, self);
+    }
+
+    pub fn played(&self, date: Date) -> bool {
+        self.results.iter().any(|result| result.date == date)
+    }
+
+    // Consecutive days played up to today, or up to yesterday while today is still open.
+    pub fn streak(&self, today: Date) -> u32 {
+        let mut day = today.to_days();
+        if !self.played(today) {
+            day -= 1;
+        }
+        let mut streak = 0;
+        while self.played(Date::from_days(day)) {
+            streak += 1;
+            day -= 1;
+        }
+        streak
+    }
+
+    pub fn best_streak(&self) -> u32 {
+        let mut days: Vec<i64> = self
+            .results
+            .iter()
+            .map(|result| result.date.to_days())
+            .collect();
+        days.sort_unstable();
+        days.dedup();
+        let mut best = 0;
+        let mut current = 0;
+        for (index, day) in days.iter().enumerate() {
+            if index > 0 && days[index - 1] + 1 == *day {
+                current += 1;
+            } else {
+                current = 1;
+            }
+            best = best.max(current);
+        }
+        best
+    }
+
+    pub fn recent(&self, count: usize) -> Vec<&DailyResult> {
+        let mut results: Vec<&DailyResult> = self.results.iter().collect();
+        results.sort_by(|a, b| b.date.cmp(&a.date));
+        results.truncate(count);
+        results
+    }
+}
+
+// The daily run in progress, only the first run of a day is scored and the rest are practice.
+#[derive(Default)]
+pub struct DailyAttempt {
+    pub date: Option<Date>,
+    pub scored: bool,
+}
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(DailyHistory::load())
+            .init_resource::<DailyAttempt>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game).with_system(
+                    begin_daily_run
+                        .system()
+                        .label(BEGIN_DAILY_RUN)
+                        .after(START_RUN),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Game).with_system(record_daily_run.system()),
+            );
+    }
+}
+
+// The scored attempt is saved as used up as soon as it starts, so quitting mid-run does not give
+// another try. Its result is filled in when the run ends.
+pub fn begin_daily_run(
+    playback: Res<Playback>,
+    mut history: ResMut<DailyHistory>,
+    mut config: ResMut<SimConfig>,
+    mut seed: ResMut<RunSeed>,
+    mut rng: ResMut<GameRng>,
+    mut attempt: ResMut<DailyAttempt>,
+) {
+    *attempt = DailyAttempt::default();
+    if config.mode != GameMode::Daily || playback.0 {
+        return;
+    }
+    let today = Date::today();
+    config.modifier = Some(Modifier::for_date(today));
+    seed.0 = daily_seed(today);
+    *rng = GameRng::new(seed.0);
+    *attempt = DailyAttempt {
+        date: Some(today),
+        scored: !history.played(today),
+    };
+    if attempt.scored {
+        history.results.push(DailyResult {
+            date: today,
+            modifier: Modifier::for_date(today),
+            time: 0f32,
+            score: 0,
+        });
+        history.save();
+    }
+}
+
+pub fn record_daily_run(
+    attempt: Res<DailyAttempt>,
+    config: Res<SimConfig>,
+    score: Res<Score>,
+    game_time: Res<GameStartTimer>,
+    mut history: ResMut<DailyHistory>,
+) {
+    if let (Some(date), Some(modifier), true) = (attempt.date, config.modifier, attempt.scored) {
+        if let Some(result) = history
+            .results
+            .iter_mut()
+            .find(|result| result.date == date)
+        {
+            *result = DailyResult {
+                date,
+                modifier,
+                time: game_time.current_time.0,
+                score: score.0,
+            };
+            history.save();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(days: &[i64]) -> DailyHistory {
+        DailyHistory {
+            results: days
+                .iter()
+                .map(|day| DailyResult {
+                    date: Date::from_days(*day),
+                    modifier: Modifier::for_date(Date::from_days(*day)),
+                    time: 10f32,
+                    score: 1,
+                })
+                .collect(),
+        }
+    }
+
+    const TODAY: i64 = 20000;
+
+    fn today() -> Date {
+        Date::from_days(TODAY)
+    }
+
+    #[test]
+    fn streak_counts_back_from_today() {
+        assert_eq!(history(&[]).streak(today()), 0);
+        assert_eq!(history(&[TODAY]).streak(today()), 1);
+        assert_eq!(history(&[TODAY - 2, TODAY, TODAY - 1]).streak(today()), 3);
+    }
+
+    #[test]
+    fn streak_keeps_today_open() {
+        assert_eq!(history(&[TODAY - 1, TODAY - 2]).streak(today()), 2);
+        assert_eq!(history(&[TODAY - 2, TODAY - 3]).streak(today()), 0);
+        assert_eq!(history(&[TODAY + 1]).streak(today()), 0);
+    }
+
+    #[test]
+    fn streak_stops_at_gaps_and_ignores_duplicates() {
+        assert_eq!(history(&[TODAY, TODAY - 1, TODAY - 3]).streak(today()), 2);
+        assert_eq!(
+            history(&[TODAY, TODAY, TODAY - 1, TODAY - 1]).streak(today()),
+            2
+        );
+    }
+
+    #[test]
+    fn streak_before_1970() {
+        let today = Date::from_days(0);
+        assert_eq!(history(&[-1, -2, -4]).streak(today), 2);
+        assert_eq!(history(&[0, -1, -2]).streak(today), 3);
+    }
+
+    #[test]
+    fn best_streak() {
+        assert_eq!(history(&[]).best_streak(), 0);
+        assert_eq!(history(&[7]).best_streak(), 1);
+        assert_eq!(history(&[10, 1, 6, 2, 5, 3]).best_streak(), 3);
+        assert_eq!(history(&[5, 6, 6, 7, 7]).best_streak(), 3);
+        assert_eq!(history(&[-2, -1, 0, 1, 9]).best_streak(), 4);
+    }
+
+    #[test]
+    fn modifiers_rotate_every_day() {
+        assert_eq!(Modifier::for_date(Date::from_days(0)), Modifier::ALL[0]);
+        assert_eq!(Modifier::for_date(Date::from_days(-1)), Modifier::ALL[2]);
+        assert_eq!(Modifier::for_date(Date::from_days(-3)), Modifier::ALL[0]);
+        for day in -10..10 {
+            let index = |day: i64| {
+                let modifier = Modifier::for_date(Date::from_days(day));
+                Modifier::ALL
+                    .iter()
+                    .position(|known| *known == modifier)
+                    .unwrap()
+            };
+            assert_eq!((index(day) + 1) % Modifier::ALL.len(), index(day + 1));
+        }
+    }
+
+    #[test]
+    fn daily_seeds_differ_by_day() {
+        let seeds: Vec<u64> = (-5..5)
+            .map(|day| daily_seed(Date::from_days(day)))
+            .collect();
+        for (index, seed) in seeds.iter().enumerate() {
+            assert!(!seeds[index + 1..].contains(seed));
+        }
+        // Every player has to get the same seed for a date, across versions too.
+        assert_eq!(daily_seed(Date::from_days(0)), 0xe220a8397b1dcdaf);
+        assert_eq!(daily_seed(today()), 0xe7aa7324ae5d121e);
+    }
+}
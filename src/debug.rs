@@ -0,0 +1,225 @@
+use crate::collision::{rotate, Collider};
+use crate::enemies::{HomingMineMarker, MINE_HOME_DISTANCE};
+use crate::player::PlayerMarker;
+use crate::sim::SimConfig;
+use crate::ui::{text_bundle, Fonts};
+use crate::world::{Force, ShapeTextures, SpawnSystem, Velocity};
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+
+const TOGGLE_KEY: KeyCode = KeyCode::F12;
+
+const DEPTH: f32 = 0.5f32;
+const LINE_WIDTH: f32 = 1.5f32;
+// Vectors are drawn as far as they would move or push things in this many seconds.
+const VELOCITY_SCALE: f32 = 0.25f32;
+const FORCE_SCALE: f32 = 0.5f32;
+
+const COUNTER_FONT_SIZE: f32 = 20f32;
+const COUNTER_MARGIN: f32 = 8f32;
+
+// Drawn every frame over the arena while the overlay is on, and thrown away the next frame.
+pub struct DebugShape;
+
+pub struct DebugCounters;
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+pub struct DebugMaterials {
+    collider: Handle<ColorMaterial>,
+    collider_ring: Handle<ColorMaterial>,
+    velocity: Handle<ColorMaterial>,
+    force: Handle<ColorMaterial>,
+    mine_home: Handle<ColorMaterial>,
+    spawn_exclusion: Handle<ColorMaterial>,
+}
+
+impl DebugMaterials {
+    fn new(materials: &mut Assets<ColorMaterial>, textures: &ShapeTextures) -> Self {
+        let mut ring =
+            |color| materials.add(ColorMaterial::modulated_texture(textures.ring(), color));
+        DebugMaterials {
+            collider_ring: ring(Color::rgba(0.2, 1.0, 0.2, 0.8)),
+            mine_home: ring(Color::rgba(1.0, 0.3, 0.3, 0.6)),
+            spawn_exclusion: ring(Color::rgba(1.0, 0.3, 1.0, 0.6)),
+            collider: materials.add(ColorMaterial::color(Color::rgba(0.2, 1.0, 0.2, 0.8))),
+            velocity: materials.add(ColorMaterial::color(Color::rgba(0.3, 0.8, 1.0, 0.9))),
+            force: materials.add(ColorMaterial::color(Color::rgba(1.0, 0.9, 0.2, 0.9))),
+        }
+    }
+}
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<DebugOverlay>()
+            .add_plugin(FrameTimeDiagnosticsPlugin::default())
+            .add_system(toggle_overlay.system())
+            .add_system(draw_overlay.system())
+            .add_system(update_debug_counters.system());
+    }
+}
+
+fn line(from: Vec2, to: Vec2, material: Handle<ColorMaterial>) -> SpriteBundle {
+    let offset = to - from;
+    let center = (from + to) * 0.5f32;
+    SpriteBundle {
+        sprite: Sprite::new(Vec2::new(offset.length(), LINE_WIDTH)),
+        material,
+        transform: Transform {
+            translation: Vec3::new(center.x, center.y, DEPTH),
+            rotation: Quat::from_rotation_z(offset.y.atan2(offset.x)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn ring(center: Vec2, radius: f32, material: Handle<ColorMaterial>) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite::new(Vec2::splat(radius * 2f32)),
+        material,
+        transform: Transform::from_xyz(center.x, center.y, DEPTH),
+        ..Default::default()
+    }
+}
+
+fn outline(collider: &Collider, center: Vec2, materials: &DebugMaterials) -> Vec<SpriteBundle> {
+    let (half_extents, angle) = match *collider {
+        Collider::Circle { radius } => {
+            return vec![ring(center, radius, materials.collider_ring.clone())]
+        }
+        Collider::Aabb { half_extents } => (half_extents, 0f32),
+        Collider::Obb {
+            half_extents,
+            angle,
+        } => (half_extents, angle),
+    };
+    let corners: Vec<Vec2> = [(-1f32, -1f32), (1f32, -1f32), (1f32, 1f32), (-1f32, 1f32)]
+        .iter()
+        .map(|(x, y)| center + rotate(half_extents * Vec2::new(*x, *y), angle))
+        .collect();
+    (0..corners.len())
+        .map(|index| {
+            line(
+                corners[index],
+                corners[(index + 1) % corners.len()],
+                materials.collider.clone(),
+            )
+        })
+        .collect()
+}
+
+pub fn toggle_overlay(keys: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+pub fn draw_overlay(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    config: Res<SimConfig>,
+    textures: Option<Res<ShapeTextures>>,
+    mut assets: ResMut<Assets<ColorMaterial>>,
+    mut materials: Local<Option<DebugMaterials>>,
+    shapes: Query<Entity, With<DebugShape>>,
+    colliders: Query<(&Collider, &Transform)>,
+    vectors: Query<
+        (&Transform, Option<&Velocity>, Option<&Force>),
+        Or<(With<Velocity>, With<Force>)>,
+    >,
+    mines: Query<&Transform, With<HomingMineMarker>>,
+    players: Query<&Transform, With<PlayerMarker>>,
+) {
+    for entity in shapes.iter() {
+        commands.entity(entity).despawn();
+    }
+    let textures = match (overlay.enabled, textures) {
+        (true, Some(textures)) => textures,
+        _ => return,
+    };
+    let materials = materials.get_or_insert_with(|| DebugMaterials::new(&mut assets, &textures));
+    let mut bundles = Vec::new();
+    for (collider, transform) in colliders.iter() {
+        let location = transform.translation.truncate();
+        bundles.extend(outline(collider, location, materials));
+    }
+    for (transform, velocity, force) in vectors.iter() {
+        let location = transform.translation.truncate();
+        if let Some(velocity) = velocity {
+            let end = location + velocity.0 * VELOCITY_SCALE;
+            bundles.push(line(location, end, materials.velocity.clone()));
+        }
+        if let Some(force) = force {
+            let end = location + force.0 * FORCE_SCALE;
+            bundles.push(line(location, end, materials.force.clone()));
+        }
+    }
+    for transform in mines.iter() {
+        bundles.push(ring(
+            transform.translation.truncate(),
+            MINE_HOME_DISTANCE,
+            materials.mine_home.clone(),
+        ));
+    }
+    let player_distance = config.difficulty.tuning().player_distance;
+    for transform in players.iter() {
+        bundles.push(ring(
+            transform.translation.truncate(),
+            player_distance,
+            materials.spawn_exclusion.clone(),
+        ));
+    }
+    for bundle in bundles {
+        commands.spawn_bundle(bundle).insert(DebugShape);
+    }
+}
+
+pub fn update_debug_counters(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    config: Res<SimConfig>,
+    diagnostics: Res<Diagnostics>,
+    fonts: Option<Res<Fonts>>,
+    spawn_system: Option<Res<SpawnSystem>>,
+    entities: Query<Entity, Without<DebugShape>>,
+    mut counters: Query<(Entity, &mut Text), With<DebugCounters>>,
+) {
+    if !overlay.enabled {
+        for (entity, _) in counters.iter_mut() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.average())
+        .unwrap_or(0f64);
+    let mut value = format!("Entities {}\nFPS {:.0}", entities.iter().count(), fps);
+    if let Some(spawn_system) = spawn_system {
+        value = format!(
+            "{}\nSpawn interval {:.2}s\nDifficulty {:.2} ({:?})",
+            value,
+            spawn_system.interval(&config.difficulty.tuning()),
+            spawn_system.difficulty(),
+            config.difficulty
+        );
+    }
+    if let Ok((_, mut text)) = counters.single_mut() {
+        text.sections[0].value = value;
+    } else if let Some(fonts) = fonts {
+        let mut counters = text_bundle(value, &fonts, COUNTER_FONT_SIZE, Color::WHITE, 0f32);
+        counters.style.position_type = PositionType::Absolute;
+        counters.style.position = Rect {
+            top: Val::Px(COUNTER_MARGIN),
+            right: Val::Px(COUNTER_MARGIN),
+            ..Default::default()
+        };
+        commands.spawn_bundle(counters).insert(DebugCounters);
+    }
+}
@@ -1,12 +1,13 @@
+use crate::collision::Collider;
+use crate::physics::PhysicsBundle;
 use crate::player::PlayerMarker;
+use crate::sim::SimClock;
 use crate::walls::{WallDeathMarker, WallMarker};
-use crate::world::{
-    BounceMarker, CharType, DefaultSize, Force, Location, MaterialResource, ObjectMarker, Velocity,
-};
+use crate::world::{BounceMarker, CharType, Force, MaterialResource, ObjectMarker, Velocity};
 use bevy::prelude::*;
 use bevy::sprite::collide_aabb::{collide, Collision};
 use rand::distributions::Uniform;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 const BOUNCING_ENEMY_SIZE: f32 = 20f32;
 const BOUNCING_ENEMY_VELOCITY: f32 = 225f32;
@@ -14,58 +15,62 @@ const TEAM: u8 = 1;
 
 const HOMING_MINE_SIZE: f32 = 20f32;
 const HOMING_MINE_FORCE: f32 = 125f32;
-const MINE_HOME_DISTANCE: f32 = 250f32;
+const HOMING_MINE_MASS: f32 = 1f32;
+const HOMING_MINE_DRAG: f32 = 0.6f32;
+pub const MINE_HOME_DISTANCE: f32 = 250f32;
 const MINE_SPIN_SPEED: f32 = std::f32::consts::PI * 2f32;
+const MINE_RING_SHOW_DISTANCE: f32 = MINE_HOME_DISTANCE * 1.5f32;
+const MINE_RING_DEPTH: f32 = -0.002;
 
 #[derive(Bundle)]
 pub struct BouncingEnemyBundle {
     marker: BounceMarker,
     object_marker: ObjectMarker,
     type_marker: CharType,
-    size: DefaultSize,
-    location: Location,
+    collider: Collider,
     #[bundle]
     sprite: SpriteBundle,
     velocity: Velocity,
 }
 
-pub fn new_bouncing_enemy(material: Handle<ColorMaterial>, location: Vec2) -> BouncingEnemyBundle {
+pub fn new_bouncing_enemy<R: Rng>(
+    material: Handle<ColorMaterial>,
+    location: Vec2,
+    rng: &mut R,
+) -> BouncingEnemyBundle {
     let sprite_bundle = SpriteBundle {
         sprite: Sprite::new(Vec2::new(BOUNCING_ENEMY_SIZE, BOUNCING_ENEMY_SIZE)),
         material: material.clone(),
+        transform: Transform::from_xyz(location.x, location.y, 0f32),
         ..Default::default()
     };
 
     let uniform = Uniform::new(0f32, std::f32::consts::PI * 2f32);
-    let angle = thread_rng().sample(uniform);
+    let angle = rng.sample(uniform);
     let sin_cos = angle.sin_cos();
     let bouncing_enemy_bundle = BouncingEnemyBundle {
         marker: BounceMarker,
         object_marker: ObjectMarker(TEAM),
         type_marker: CharType::Enemy,
-        size: DefaultSize {
-            width: BOUNCING_ENEMY_SIZE,
-            height: BOUNCING_ENEMY_SIZE,
-        },
+        collider: Collider::circle(BOUNCING_ENEMY_SIZE),
         sprite: sprite_bundle,
         velocity: Velocity(Vec2::new(
             sin_cos.0 * BOUNCING_ENEMY_VELOCITY,
             sin_cos.1 * BOUNCING_ENEMY_VELOCITY,
         )),
-        location: Location(location),
     };
     bouncing_enemy_bundle
 }
 
 pub fn move_bouncing_enemy(
-    time: Res<Time>,
-    mut bouncing_enemy: Query<(&BounceMarker, &mut Location, &Velocity)>,
+    clock: Res<SimClock>,
+    mut bouncing_enemy: Query<(&BounceMarker, &mut Transform, &Velocity)>,
 ) {
-    let delta_seconds = time.delta_seconds();
-    for (_, mut location, velocity) in bouncing_enemy.iter_mut() {
+    let delta_seconds = clock.delta;
+    for (_, mut transform, velocity) in bouncing_enemy.iter_mut() {
         let move_amt = velocity.0 * delta_seconds;
-        location.0.x += move_amt.x;
-        location.0.y += move_amt.y;
+        transform.translation.x += move_amt.x;
+        transform.translation.y += move_amt.y;
     }
 }
 
@@ -77,10 +82,9 @@ pub struct HomingMineBundle {
     obj_marker: ObjectMarker,
     wall_death_marker: WallDeathMarker,
     type_marker: CharType,
-    size: DefaultSize,
-    location: Location,
-    force: Force,
-    velocity: Velocity,
+    collider: Collider,
+    #[bundle]
+    physics: PhysicsBundle,
     #[bundle]
     sprite: SpriteBundle,
 }
@@ -89,6 +93,7 @@ pub fn new_homing_mine(material: Handle<ColorMaterial>, location: Vec2) -> Homin
     let sprite_bundle = SpriteBundle {
         sprite: Sprite::new(Vec2::new(HOMING_MINE_SIZE, HOMING_MINE_SIZE)),
         material: material.clone(),
+        transform: Transform::from_xyz(location.x, location.y, 0f32),
         ..Default::default()
     };
     let homing_mine_bundle = HomingMineBundle {
@@ -96,37 +101,98 @@ pub fn new_homing_mine(material: Handle<ColorMaterial>, location: Vec2) -> Homin
         obj_marker: ObjectMarker(TEAM),
         wall_death_marker: WallDeathMarker,
         type_marker: CharType::Enemy,
-        size: DefaultSize {
-            width: HOMING_MINE_SIZE,
-            height: HOMING_MINE_SIZE,
-        },
-        location: Location(location),
-        force: Force(Vec2::default()),
-        velocity: Velocity(Vec2::default()),
+        collider: Collider::obb(HOMING_MINE_SIZE, HOMING_MINE_SIZE, 0f32),
+        physics: PhysicsBundle::new(HOMING_MINE_MASS, HOMING_MINE_DRAG),
         sprite: sprite_bundle,
     };
     homing_mine_bundle
 }
 
+fn nearest(players: &[Vec2], location: Vec2) -> Option<Vec2> {
+    players.iter().copied().min_by(|a, b| {
+        a.distance_squared(location)
+            .partial_cmp(&b.distance_squared(location))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })
+}
+
+// Mines home in on the nearest player.
 pub fn update_mines(
-    mut homing_mines: Query<(&HomingMineMarker, &Location, &mut Force)>,
-    mut player: Query<(&PlayerMarker, &Location)>,
+    mut homing_mines: Query<(&HomingMineMarker, &Transform, &mut Force)>,
+    players: Query<&Transform, With<PlayerMarker>>,
 ) {
-    if let Ok((_, player_loc)) = player.single_mut() {
-        for (_, mine_loc, mut force) in homing_mines.iter_mut() {
-            if mine_loc.0.distance_squared(player_loc.0) < MINE_HOME_DISTANCE * MINE_HOME_DISTANCE {
-                force.0 = (player_loc.0 - mine_loc.0).normalize() * HOMING_MINE_FORCE;
+    let players: Vec<Vec2> = players
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    for (_, transform, mut force) in homing_mines.iter_mut() {
+        let mine_loc = transform.translation.truncate();
+        if let Some(player_loc) = nearest(&players, mine_loc) {
+            if mine_loc.distance_squared(player_loc) < MINE_HOME_DISTANCE * MINE_HOME_DISTANCE {
+                force.0 = (player_loc - mine_loc).normalize() * HOMING_MINE_FORCE;
             }
         }
     }
 }
 
 pub fn homing_mine_spin(
-    time: Res<Time>,
-    mut homing_mines: Query<(&HomingMineMarker, &mut Transform)>,
+    clock: Res<SimClock>,
+    mut homing_mines: Query<(&HomingMineMarker, &mut Transform, &mut Collider)>,
 ) {
-    let delta_seconds = time.delta_seconds();
-    for (_, mut transform) in homing_mines.iter_mut() {
-        transform.rotate(Quat::from_rotation_z(delta_seconds * MINE_SPIN_SPEED));
+    let delta_seconds = clock.delta;
+    for (_, mut transform, mut collider) in homing_mines.iter_mut() {
+        if let Collider::Obb { angle, .. } = &mut *collider {
+            *angle = (*angle + delta_seconds * MINE_SPIN_SPEED) % (std::f32::consts::PI * 2f32);
+            transform.rotation = Quat::from_rotation_z(*angle);
+        }
+    }
+}
+
+pub struct MineRingMarker;
+
+#[derive(Bundle)]
+pub struct MineRingBundle {
+    marker: MineRingMarker,
+    #[bundle]
+    sprite: SpriteBundle,
+}
+
+pub fn new_mine_ring(material: Handle<ColorMaterial>) -> MineRingBundle {
+    let diameter = MINE_HOME_DISTANCE * 2f32;
+    MineRingBundle {
+        marker: MineRingMarker,
+        sprite: SpriteBundle {
+            sprite: Sprite::new(Vec2::new(diameter, diameter)),
+            material,
+            transform: Transform::from_xyz(0f32, 0f32, MINE_RING_DEPTH),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        },
+    }
+}
+
+pub fn update_mine_rings(
+    players: Query<&Transform, With<PlayerMarker>>,
+    homing_mines: Query<(&HomingMineMarker, &Transform, &Children)>,
+    mut rings: Query<&mut Visible, With<MineRingMarker>>,
+) {
+    let players: Vec<Vec2> = players
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    for (_, transform, children) in homing_mines.iter() {
+        let mine_loc = transform.translation.truncate();
+        let near = nearest(&players, mine_loc).map_or(false, |player_loc| {
+            mine_loc.distance_squared(player_loc)
+                < MINE_RING_SHOW_DISTANCE * MINE_RING_SHOW_DISTANCE
+        });
+        for child in children.iter() {
+            if let Ok(mut visible) = rings.get_mut(*child) {
+                visible.is_visible = near;
+            }
+        }
     }
 }
@@ -0,0 +1,53 @@
+use crate::world::CharType;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct DeathEvent {
+    pub location: Vec2,
+    pub char_type: CharType,
+    pub cause: DeathCause,
+}
+
+// What an object died to, the other object for collisions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum DeathCause {
+    Wall,
+    Player,
+    Bullet,
+    BouncingEnemy,
+    HomingMine,
+}
+
+impl DeathCause {
+    pub const ALL: [DeathCause; 5] = [
+        DeathCause::Wall,
+        DeathCause::Player,
+        DeathCause::Bullet,
+        DeathCause::BouncingEnemy,
+        DeathCause::HomingMine,
+    ];
+
+    pub fn of(char_type: CharType, homing_mine: bool) -> Self {
+        match char_type {
+            CharType::Player => DeathCause::Player,
+            CharType::Bullet => DeathCause::Bullet,
+            CharType::Enemy if homing_mine => DeathCause::HomingMine,
+            CharType::Enemy => DeathCause::BouncingEnemy,
+        }
+    }
+}
+
+pub struct FireEvent {
+    pub location: Vec2,
+    pub direction: Vec2,
+}
+
+pub struct ThrustEvent {
+    pub location: Vec2,
+    pub direction: Vec2,
+}
+
+pub struct SpawnEvent {
+    pub location: Vec2,
+    pub char_type: CharType,
+}
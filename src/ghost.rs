@@ -0,0 +1,198 @@
+use crate::daily::BEGIN_DAILY_RUN;
+use crate::leaderboard::{Leaderboard, SortKey};
+use crate::player::{PlayerMarker, PlayerSlot, PLAYER_SIZE};
+use crate::rng::RunSeed;
+use crate::settings::Settings;
+use crate::sim::{HeadlessSim, Playback, Replay, SimClock, SimConfig};
+use crate::storage;
+use crate::world::{CharType, MaterialResource};
+use crate::GameState;
+use bevy::ecs::system::NonSendMut;
+use bevy::prelude::*;
+
+// Between the background and the live sprites.
+const GHOST_DEPTH: f32 = -0.005f32;
+
+pub struct GhostMarker;
+
+pub struct GhostEnemyMarker;
+
+// Ghosts are only a sprite, so nothing collides with them or kills them.
+#[derive(Bundle)]
+pub struct GhostBundle {
+    #[bundle]
+    sprite: SpriteBundle,
+}
+
+pub fn new_ghost(material: Handle<ColorMaterial>, size: Vec2, location: Vec2) -> GhostBundle {
+    GhostBundle {
+        sprite: SpriteBundle {
+            sprite: Sprite::new(size),
+            material,
+            transform: Transform::from_xyz(location.x, location.y, GHOST_DEPTH),
+            visible: Visible {
+                is_visible: true,
+                is_transparent: true,
+            },
+            ..Default::default()
+        },
+    }
+}
+
+// The best run being raced, played back from its inputs alongside the live run. Its player moves
+// the ghost player, and when it was played on the same seed its enemies move the ghost enemies.
+#[derive(Default)]
+pub struct GhostRun {
+    sim: Option<HeadlessSim>,
+    show_enemies: bool,
+    enemies: Vec<Entity>,
+}
+
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_non_send_resource(GhostRun::default())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game)
+                    .with_system(start_ghost.system().after(BEGIN_DAILY_RUN)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Game).with_system(update_ghost.system()),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Game).with_system(clear_ghost.system()));
+    }
+}
+
+fn best_replay(leaderboard: &Leaderboard, config: &SimConfig) -> Option<Replay> {
+    leaderboard
+        .sorted(config.mode, config.difficulty, SortKey::Time)
+        .into_iter()
+        .filter_map(|entry| entry.replay.as_ref())
+        .find_map(|key| storage::load::<Replay>(key))
+}
+
+fn first_player(world: &mut World) -> Option<Vec2> {
+    world
+        .query_filtered::<(&PlayerSlot, &Transform), With<PlayerMarker>>()
+        .iter(world)
+        .find(|(slot, _)| slot.0 == 0)
+        .map(|(_, transform)| transform.translation.truncate())
+}
+
+pub fn start_ghost(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    config: Res<SimConfig>,
+    seed: Res<RunSeed>,
+    playback: Res<Playback>,
+    leaderboard: Res<Leaderboard>,
+    materials: Res<MaterialResource>,
+    mut ghost: NonSendMut<GhostRun>,
+) {
+    *ghost = GhostRun::default();
+    if !settings.ghost || playback.0 {
+        return;
+    }
+    let replay = match best_replay(&leaderboard, &config) {
+        Some(replay) => replay,
+        None => return,
+    };
+    ghost.show_enemies =
+        settings.ghost_enemies && replay.seed == seed.0 && replay.config == *config;
+    let mut sim = HeadlessSim::new(&replay);
+    let start = first_player(sim.world()).unwrap_or_default();
+    ghost.sim = Some(sim);
+    commands
+        .spawn_bundle(new_ghost(
+            materials.ghost_material.clone(),
+            Vec2::splat(PLAYER_SIZE),
+            start,
+        ))
+        .insert(GhostMarker);
+}
+
+pub fn update_ghost(
+    mut commands: Commands,
+    clock: Res<SimClock>,
+    materials: Res<MaterialResource>,
+    mut ghost: NonSendMut<GhostRun>,
+    mut player: Query<
+        (&mut Transform, &mut Visible),
+        (With<GhostMarker>, Without<GhostEnemyMarker>),
+    >,
+    mut enemies: Query<
+        (&mut Transform, &mut Sprite, &mut Visible),
+        (With<GhostEnemyMarker>, Without<GhostMarker>),
+    >,
+) {
+    let GhostRun {
+        sim,
+        show_enemies,
+        enemies: pool,
+    } = &mut *ghost;
+    let sim = match sim {
+        Some(sim) => sim,
+        None => return,
+    };
+    // Both runs are at the end of the same tick.
+    sim.advance_to(clock.tick);
+    let world = sim.world();
+    if let Ok((mut transform, mut visible)) = player.single_mut() {
+        match first_player(world) {
+            Some(point) => {
+                transform.translation.x = point.x;
+                transform.translation.y = point.y;
+            }
+            None => visible.is_visible = false,
+        }
+    }
+    if !*show_enemies {
+        return;
+    }
+    let shown: Vec<(Vec2, Vec2)> = world
+        .query::<(&Transform, &CharType, &Sprite)>()
+        .iter(world)
+        .filter(|(_, char_type, _)| **char_type == CharType::Enemy)
+        .map(|(transform, _, sprite)| (transform.translation.truncate(), sprite.size))
+        .collect();
+    for (index, (point, size)) in shown.iter().enumerate() {
+        match pool.get(index) {
+            Some(entity) => {
+                if let Ok((mut transform, mut sprite, mut visible)) = enemies.get_mut(*entity) {
+                    transform.translation.x = point.x;
+                    transform.translation.y = point.y;
+                    sprite.size = *size;
+                    visible.is_visible = true;
+                }
+            }
+            None => {
+                let entity = commands
+                    .spawn_bundle(new_ghost(
+                        materials.ghost_enemy_material.clone(),
+                        *size,
+                        *point,
+                    ))
+                    .insert(GhostEnemyMarker)
+                    .id();
+                pool.push(entity);
+            }
+        }
+    }
+    for entity in pool.iter().skip(shown.len()) {
+        if let Ok((_, _, mut visible)) = enemies.get_mut(*entity) {
+            visible.is_visible = false;
+        }
+    }
+}
+
+pub fn clear_ghost(
+    mut commands: Commands,
+    mut ghost: NonSendMut<GhostRun>,
+    ghosts: Query<Entity, Or<(With<GhostMarker>, With<GhostEnemyMarker>)>>,
+) {
+    for entity in ghosts.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *ghost = GhostRun::default();
+}
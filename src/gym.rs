@@ -0,0 +1,156 @@
+use crate::bot::{BotRun, Observation};
+use crate::sim::SimConfig;
+use crate::world::{CharType, ARENA_SIZE};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// How many of the nearest threats an observation holds, and the numbers per threat and for the
+// player.
+pub const OBSERVED_THREATS: usize = 8;
+pub const THREAT_FEATURES: usize = 7;
+pub const PLAYER_FEATURES: usize = 6;
+pub const OBSERVATION_SIZE: usize = PLAYER_FEATURES + OBSERVED_THREATS * THREAT_FEATURES;
+
+// Velocities are scaled by this so they end up in about the same range as positions.
+const VELOCITY_SCALE: f32 = 300f32;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rewards {
+    pub survival: f32,
+    pub kill: f32,
+    pub death: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Rewards {
+            survival: 1f32,
+            kill: 0f32,
+            death: -10f32,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvConfig {
+    pub sim: SimConfig,
+    pub rewards: Rewards,
+    // Ticks simulated per step, the action is only applied on the first.
+    pub ticks_per_step: u64,
+    pub max_ticks: u64,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            sim: SimConfig::default(),
+            rewards: Rewards::default(),
+            ticks_per_step: 4,
+            max_ticks: 60 * 60 * 10,
+        }
+    }
+}
+
+// A switch press at `aim` in arena coordinates, or nothing when `press` is false.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Action {
+    pub press: bool,
+    pub aim: [f32; 2],
+}
+
+#[derive(Serialize)]
+pub struct Step {
+    pub obs: Vec<f32>,
+    pub reward: f32,
+    pub done: bool,
+    pub time: f32,
+    pub score: u32,
+}
+
+// The player first, then the nearest threats relative to the player, padded with zeros. All
+// positions are in half arena sizes.
+pub fn encode(observation: &Observation) -> Vec<f32> {
+    let scale = ARENA_SIZE * 0.5f32;
+    let mut obs = Vec::with_capacity(OBSERVATION_SIZE);
+    let player = match &observation.player {
+        Some(player) => player,
+        None => return vec![0f32; OBSERVATION_SIZE],
+    };
+    obs.extend_from_slice(&[
+        player.location.x / scale,
+        player.location.y / scale,
+        player.velocity.x / VELOCITY_SCALE,
+        player.velocity.y / VELOCITY_SCALE,
+        player.thrust_next as u8 as f32,
+        player.ready as u8 as f32,
+    ]);
+    for threat in observation.threats().iter().take(OBSERVED_THREATS) {
+        let offset = (threat.location - player.location) / scale;
+        let velocity = threat.velocity / VELOCITY_SCALE;
+        obs.extend_from_slice(&[
+            1f32,
+            offset.x,
+            offset.y,
+            velocity.x,
+            velocity.y,
+            (threat.char_type == CharType::Enemy) as u8 as f32,
+            (threat.char_type == CharType::Bullet) as u8 as f32,
+        ]);
+    }
+    obs.resize(OBSERVATION_SIZE, 0f32);
+    obs
+}
+
+// One headless game at a time, driven a step at a time.
+pub struct Env {
+    pub config: EnvConfig,
+    run: Option<BotRun>,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Self {
+        Env { config, run: None }
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        let mut run = BotRun::new(self.config.sim, seed);
+        let obs = encode(&run.observe());
+        self.run = Some(run);
+        obs
+    }
+
+    // `None` until the first reset.
+    pub fn step(&mut self, action: Action) -> Option<Step> {
+        let config = self.config;
+        let run = self.run.as_mut()?;
+        let before = run.result();
+        if !before.finished && before.ticks < config.max_ticks {
+            if action.press {
+                run.press(Vec2::from(action.aim));
+            }
+            for _ in 0..config.ticks_per_step {
+                run.step();
+                let result = run.result();
+                if result.finished || result.ticks >= config.max_ticks {
+                    break;
+                }
+            }
+        }
+        let after = run.result();
+        let mut reward = (after.time - before.time) * config.rewards.survival
+            + after.score.saturating_sub(before.score) as f32 * config.rewards.kill;
+        if after.finished && !before.finished {
+            reward += config.rewards.death;
+        }
+        Some(Step {
+            obs: encode(&run.observe()),
+            reward,
+            done: after.finished || after.ticks >= config.max_ticks,
+            time: after.time,
+            score: after.score,
+        })
+    }
+}
@@ -0,0 +1,605 @@
+use crate::daily::{DailyAttempt, DailyHistory};
+use crate::date::{unix_seconds, Date};
+use crate::menu::{
+    cycle, despawn_menu, menu_root, spawn_buttons, spawn_title, GameMode, MenuAction, MenuButton,
+    MenuEvent, MenuScreenMarker,
+};
+use crate::palette::{ActivePalette, TextColorRole};
+use crate::rng::RunSeed;
+use crate::settings::Settings;
+use crate::sim::{Cheated, InputLog, Replay, SimConfig};
+use crate::stats::Score;
+use crate::storage;
+use crate::ui::{text_bundle, BestTime, Fonts, GameStartTimer};
+use crate::world::{Difficulty, MaterialResource};
+use crate::GameState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+const LEADERBOARD_KEY: &str = "leaderboard";
+const TABLE_SIZE: usize = 10;
+const NAME_LENGTH: usize = 12;
+const DEFAULT_NAME: &str = "Anonymous";
+const REPLAY_KEY_PREFIX: &str = "replay-";
+const LINE_FONT_SIZE: f32 = 24f32;
+const LINE_SPACING: f32 = 2f32;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub time: f32,
+    pub score: u32,
+    pub seed: u64,
+    pub date: Date,
+    #[serde(default)]
+    pub replay: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LeaderboardTable {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SortKey {
+    Time,
+    Score,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 2] = [SortKey::Time, SortKey::Score];
+
+    fn compare(self, a: &LeaderboardEntry, b: &LeaderboardEntry) -> Ordering {
+        let by_time = b.time.partial_cmp(&a.time).unwrap_or(Ordering::Equal);
+        let by_score = b.score.cmp(&a.score);
+        match self {
+            SortKey::Time => by_time.then(by_score),
+            SortKey::Score => by_score.then(by_time),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Leaderboard {
+    pub tables: Vec<LeaderboardTable>,
+}
+
+impl Leaderboard {
+    pub fn load() -> Self {
+        storage::load(LEADERBOARD_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(LEADERBOARD_KEY, self);
+    }
+
+    fn table(&self, mode: GameMode, difficulty: Difficulty) -> Option<&LeaderboardTable> {
+        self.tables
+            .iter()
+            .find(|table| table.mode == mode && table.difficulty == difficulty)
+    }
+
+    pub fn sorted(
+        &self,
+        mode: GameMode,
+        difficulty: Difficulty,
+        sort: SortKey,
+    ) -> Vec<&LeaderboardEntry> {
+        let mut entries: Vec<&LeaderboardEntry> = self
+            .table(mode, difficulty)
+            .map_or_else(Vec::new, |table| table.entries.iter().collect());
+        entries.sort_by(|a, b| sort.compare(a, b));
+        entries.truncate(TABLE_SIZE);
+        entries
+    }
+
+    pub fn best_time(&self, mode: GameMode, difficulty: Difficulty) -> Option<f32> {
+        self.sorted(mode, difficulty, SortKey::Time)
+            .first()
+            .map(|entry| entry.time)
+    }
+
+    // A run makes the table if it would place in the top entries by either key.
+    pub fn qualifies(&self, mode: GameMode, difficulty: Difficulty, time: f32, score: u32) -> bool {
+        let candidate = LeaderboardEntry {
+            name: String::new(),
+            time,
+            score,
+            seed: 0,
+            date: Date::from_days(0),
+            replay: None,
+        };
+        SortKey::ALL.iter().any(|sort| {
+            let entries = self.sorted(mode, difficulty, *sort);
+            entries.len() < TABLE_SIZE
+                || entries.last().map_or(true, |last| {
+                    sort.compare(&candidate, last) == Ordering::Less
+                })
+        })
+    }
+
+    // A key for the replay of a new entry that no entry uses yet.
+    pub fn replay_key(&self, mode: GameMode, difficulty: Difficulty, seed: u64) -> String {
+        let base = format!(
+            "{}{:?}-{:?}-{}-{}",
+            REPLAY_KEY_PREFIX,
+            mode,
+            difficulty,
+            seed,
+            unix_seconds()
+        );
+        let taken = |key: &str| {
+            self.tables
+                .iter()
+                .flat_map(|table| table.entries.iter())
+                .any(|entry| entry.replay.as_deref() == Some(key))
+        };
+        let mut key = base.clone();
+        let mut suffix = 1;
+        while taken(&key) {
+            key = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        key
+    }
+
+    // Returns the entries that fell off the table.
+    pub fn insert(
+        &mut self,
+        mode: GameMode,
+        difficulty: Difficulty,
+        entry: LeaderboardEntry,
+    ) -> Vec<LeaderboardEntry> {
+        if self.table(mode, difficulty).is_none() {
+            self.tables.push(LeaderboardTable {
+                mode,
+                difficulty,
+                entries: Vec::new(),
+            });
+        }
+        let table = self
+            .tables
+            .iter_mut()
+            .find(|table| table.mode == mode && table.difficulty == difficulty)
+            .unwrap();
+        table.entries.push(entry);
+        // Keep whatever is still in the top entries by time or by score.
+        let mut kept = vec![false; table.entries.len()];
+        for sort in SortKey::ALL.iter() {
+            let mut order: Vec<usize> = (0..table.entries.len()).collect();
+            order.sort_by(|a, b| sort.compare(&table.entries[*a], &table.entries[*b]));
+            for index in order.into_iter().take(TABLE_SIZE) {
+                kept[index] = true;
+            }
+        }
+        let (kept, dropped) = table
+            .entries
+            .drain(..)
+            .zip(kept)
+            .partition::<Vec<_>, _>(|(_, kept)| *kept);
+        table.entries = kept.into_iter().map(|(entry, _)| entry).collect();
+        dropped.into_iter().map(|(entry, _)| entry).collect()
+    }
+}
+
+// Result of the run that just ended, shown on the game over screen.
+#[derive(Default)]
+pub struct LastRun {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub time: f32,
+    pub score: u32,
+    pub seed: u64,
+    pub recording: Replay,
+    pub cheated: bool,
+    pub qualifies: bool,
+}
+
+// Best times of this session per mode and difficulty, including runs that were never saved.
+#[derive(Default)]
+pub struct SessionBests(Vec<(GameMode, Difficulty, f32)>);
+
+impl SessionBests {
+    fn get(&self, mode: GameMode, difficulty: Difficulty) -> f32 {
+        self.0
+            .iter()
+            .find(|(best_mode, best_difficulty, _)| {
+                *best_mode == mode && *best_difficulty == difficulty
+            })
+            .map_or(0f32, |(_, _, time)| *time)
+    }
+
+    fn raise(&mut self, mode: GameMode, difficulty: Difficulty, time: f32) {
+        match self.0.iter_mut().find(|(best_mode, best_difficulty, _)| {
+            *best_mode == mode && *best_difficulty == difficulty
+        }) {
+            Some((_, _, best)) => *best = best.max(time),
+            None => self.0.push((mode, difficulty, time)),
+        }
+    }
+}
+
+pub struct LeaderboardView {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub sort: SortKey,
+}
+
+pub struct NameEntry(pub String);
+
+pub struct NameLabelMarker;
+
+pub struct LeaderboardRowsMarker;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(Leaderboard::load())
+            .insert_resource(LeaderboardView {
+                mode: GameMode::default(),
+                difficulty: Difficulty::default(),
+                sort: SortKey::Time,
+            })
+            .insert_resource(NameEntry(String::new()))
+            .init_resource::<LastRun>()
+            .init_resource::<SessionBests>()
+            .add_system(handle_leaderboard_events.system())
+            .add_system(update_board_labels.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game).with_system(load_best_time.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Game)
+                    .with_system(record_last_run.system())
+                    .with_system(record_session_best.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(spawn_game_over_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver)
+                    .with_system(type_name.system())
+                    .with_system(update_name_label.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver).with_system(despawn_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Leaderboard)
+                    .with_system(spawn_leaderboard_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Leaderboard)
+                    .with_system(refresh_leaderboard_rows.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Leaderboard).with_system(despawn_menu.system()),
+            );
+    }
+}
+
+pub fn load_best_time(
+    leaderboard: Res<Leaderboard>,
+    sessions: Res<SessionBests>,
+    settings: Res<Settings>,
+    mut best_time: ResMut<BestTime>,
+) {
+    best_time.0 = leaderboard
+        .best_time(settings.game_mode, settings.difficulty)
+        .unwrap_or(0f32)
+        .max(sessions.get(settings.game_mode, settings.difficulty));
+}
+
+pub fn record_session_best(
+    config: Res<SimConfig>,
+    game_time: Res<GameStartTimer>,
+    mut sessions: ResMut<SessionBests>,
+) {
+    sessions.raise(config.mode, config.difficulty, game_time.current_time.0);
+}
+
+pub fn record_last_run(
+    mut last_run: ResMut<LastRun>,
+    leaderboard: Res<Leaderboard>,
+    settings: Res<Settings>,
+    config: Res<SimConfig>,
+    score: Res<Score>,
+    seed: Res<RunSeed>,
+    game_time: Res<GameStartTimer>,
+    log: Res<InputLog>,
+    attempt: Res<DailyAttempt>,
+    cheated: Res<Cheated>,
+    mut name: ResMut<NameEntry>,
+) {
+    let time = game_time.current_time.0;
+    let practice = config.mode == GameMode::Daily && !attempt.scored;
+    let qualifies = config.mode.ranked()
+        && !practice
+        && !cheated.0
+        && leaderboard.qualifies(config.mode, config.difficulty, time, score.0);
+    name.0 = settings.player_name.clone();
+    *last_run = LastRun {
+        mode: config.mode,
+        difficulty: config.difficulty,
+        time,
+        score: score.0,
+        seed: seed.0,
+        recording: Replay {
+            config: *config,
+            seed: seed.0,
+            inputs: log.0.clone(),
+        },
+        cheated: cheated.0,
+        qualifies,
+    };
+}
+
+pub fn entry_name(name: &NameEntry) -> String {
+    let name = name.0.trim();
+    if name.is_empty() {
+        DEFAULT_NAME.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn name_label(name: &NameEntry) -> String {
+    format!("Name: {}_", name.0)
+}
+
+pub fn spawn_game_over_screen(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    materials: Res<MaterialResource>,
+    palette: Res<ActivePalette>,
+    last_run: Res<LastRun>,
+    name: Res<NameEntry>,
+    attempt: Res<DailyAttempt>,
+    history: Res<DailyHistory>,
+) {
+    // Versus rounds end on the versus screen instead.
+    if last_run.mode == GameMode::Versus {
+        return;
+    }
+    let mut lines = vec![format!(
+        "Time {:.1}   Score {}",
+        last_run.time, last_run.score
+    )];
+    if let (Some(date), Some(modifier)) = (attempt.date, last_run.recording.config.modifier) {
+        lines.push(format!("Daily {}: {}", date, modifier.name()));
+        if attempt.scored {
+            lines.push(format!("Daily streak: {}", history.streak(date)));
+        } else {
+            lines.push("Practice run".to_string());
+        }
+    }
+    if last_run.cheated {
+        lines.push("Console used, not ranked".to_string());
+    }
+    let actions = if last_run.qualifies {
+        lines.push("New leaderboard entry!".to_string());
+        vec![MenuAction::SaveScore, MenuAction::Continue]
+    } else {
+        vec![MenuAction::Continue]
+    };
+    commands
+        .spawn_bundle(menu_root(&materials))
+        .insert(MenuScreenMarker)
+        .with_children(|parent| {
+            spawn_title(parent, "Game Over", &fonts, &palette);
+            for line in lines.iter() {
+                parent
+                    .spawn_bundle(text_bundle(
+                        line.clone(),
+                        &fonts,
+                        LINE_FONT_SIZE,
+                        palette.palette.text,
+                        LINE_SPACING,
+                    ))
+                    .insert(TextColorRole::Text);
+            }
+            if last_run.qualifies {
+                parent
+                    .spawn_bundle(text_bundle(
+                        name_label(&name),
+                        &fonts,
+                        LINE_FONT_SIZE,
+                        palette.palette.text,
+                        LINE_SPACING,
+                    ))
+                    .insert(TextColorRole::Text)
+                    .insert(NameLabelMarker);
+            }
+            spawn_buttons(parent, &actions, &fonts, &materials, &palette);
+        });
+}
+
+pub fn type_name(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut name: ResMut<NameEntry>,
+) {
+    for character in characters.iter() {
+        let valid = character.char.is_alphanumeric() || character.char == ' ';
+        if valid && name.0.chars().count() < NAME_LENGTH {
+            name.0.push(character.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        name.0.pop();
+    }
+}
+
+pub fn update_name_label(name: Res<NameEntry>, mut label: Query<&mut Text, With<NameLabelMarker>>) {
+    if name.is_changed() {
+        if let Ok(mut text) = label.single_mut() {
+            text.sections[0].value = name_label(&name);
+        }
+    }
+}
+
+pub fn spawn_leaderboard_screen(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    materials: Res<MaterialResource>,
+    palette: Res<ActivePalette>,
+) {
+    let actions = [
+        MenuAction::BoardMode,
+        MenuAction::BoardDifficulty,
+        MenuAction::BoardSort,
+        MenuAction::Back,
+    ];
+    commands
+        .spawn_bundle(menu_root(&materials))
+        .insert(MenuScreenMarker)
+        .with_children(|parent| {
+            spawn_title(parent, "Leaderboard", &fonts, &palette);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: materials.panel_material.clone(),
+                    ..Default::default()
+                })
+                .insert(LeaderboardRowsMarker);
+            spawn_buttons(parent, &actions, &fonts, &materials, &palette);
+        });
+}
+
+fn entry_line(rank: usize, entry: &LeaderboardEntry) -> String {
+    format!(
+        "{:>2}. {:<12} {:>7.1} {:>5}  {}",
+        rank, entry.name, entry.time, entry.score, entry.date
+    )
+}
+
+pub fn refresh_leaderboard_rows(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    palette: Res<ActivePalette>,
+    leaderboard: Res<Leaderboard>,
+    view: Res<LeaderboardView>,
+    rows: Query<(Entity, Option<&Children>), With<LeaderboardRowsMarker>>,
+    added: Query<Entity, Added<LeaderboardRowsMarker>>,
+) {
+    if !view.is_changed() && !leaderboard.is_changed() && added.iter().next().is_none() {
+        return;
+    }
+    let entries = leaderboard.sorted(view.mode, view.difficulty, view.sort);
+    let mut lines: Vec<String> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| entry_line(index + 1, entry))
+        .collect();
+    if lines.is_empty() {
+        lines.push("No entries yet".to_string());
+    }
+    for (entity, children) in rows.iter() {
+        if let Some(children) = children {
+            for child in children.iter() {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        commands.entity(entity).with_children(|parent| {
+            for line in lines.iter() {
+                parent
+                    .spawn_bundle(text_bundle(
+                        line.clone(),
+                        &fonts,
+                        LINE_FONT_SIZE,
+                        palette.palette.text,
+                        LINE_SPACING,
+                    ))
+                    .insert(TextColorRole::Text);
+            }
+        });
+    }
+}
+
+pub fn handle_leaderboard_events(
+    mut events: EventReader<MenuEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut view: ResMut<LeaderboardView>,
+    mut settings: ResMut<Settings>,
+    last_run: Res<LastRun>,
+    name: Res<NameEntry>,
+) {
+    for event in events.iter() {
+        match event.action {
+            MenuAction::Leaderboard => {
+                view.mode = if settings.game_mode.ranked() {
+                    settings.game_mode
+                } else {
+                    GameMode::default()
+                };
+                view.difficulty = settings.difficulty;
+            }
+            MenuAction::BoardMode => view.mode = cycle(&GameMode::RANKED, view.mode, event.step),
+            MenuAction::BoardDifficulty => {
+                view.difficulty = cycle(&Difficulty::ALL, view.difficulty, event.step)
+            }
+            MenuAction::BoardSort => view.sort = cycle(&SortKey::ALL, view.sort, event.step),
+            MenuAction::SaveScore if last_run.qualifies => {
+                let name = entry_name(&name);
+                let replay =
+                    leaderboard.replay_key(last_run.mode, last_run.difficulty, last_run.seed);
+                storage::save(&replay, &last_run.recording);
+                let dropped = leaderboard.insert(
+                    last_run.mode,
+                    last_run.difficulty,
+                    LeaderboardEntry {
+                        name: name.clone(),
+                        time: last_run.time,
+                        score: last_run.score,
+                        seed: last_run.seed,
+                        date: Date::today(),
+                        replay: Some(replay),
+                    },
+                );
+                for key in dropped.iter().filter_map(|entry| entry.replay.as_ref()) {
+                    storage::remove(key);
+                }
+                leaderboard.save();
+                settings.player_name = name;
+                view.mode = last_run.mode;
+                view.difficulty = last_run.difficulty;
+                state.set(GameState::Leaderboard).unwrap_or(());
+            }
+            MenuAction::Continue => state.set(GameState::Menu).unwrap_or(()),
+            _ => {}
+        }
+    }
+}
+
+pub fn update_board_labels(
+    view: Res<LeaderboardView>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (button, children) in buttons.iter() {
+        let label = match button.action {
+            MenuAction::BoardMode => format!("Mode: {}", view.mode.name()),
+            MenuAction::BoardDifficulty => format!("Difficulty: {:?}", view.difficulty),
+            MenuAction::BoardSort => format!("Sort: {:?}", view.sort),
+            _ => continue,
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                if text.sections[0].value != label {
+                    text.sections[0].value = label.clone();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+pub mod analytics;
+pub mod audio;
+pub mod bot;
+pub mod bullet;
+pub mod camera;
+pub mod cli;
+pub mod collision;
+#[cfg(feature = "console")]
+pub mod console;
+pub mod coop;
+pub mod daily;
+pub mod date;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod enemies;
+pub mod events;
+pub mod ghost;
+pub mod gym;
+pub mod leaderboard;
+pub mod menu;
+#[cfg(all(feature = "netplay", not(target_arch = "wasm32")))]
+pub mod net;
+#[cfg(feature = "online")]
+pub mod online;
+pub mod palette;
+pub mod particles;
+pub mod physics;
+pub mod player;
+pub mod protocol;
+pub mod rng;
+pub mod settings;
+pub mod sim;
+#[cfg(all(feature = "spectate", not(target_arch = "wasm32")))]
+pub mod spectate;
+pub mod stats;
+pub mod storage;
+pub mod ui;
+pub mod versus;
+pub mod walls;
+pub mod world;
+
+
+use crate::audio::GameAudioPlugin;
+use crate::camera::CameraPlugin;
+use crate::cli::{run_headless, LaunchOptions, LaunchPlugin, USAGE};
+use crate::daily::DailyPlugin;
+use crate::ghost::GhostPlugin;
+use crate::enemies::update_mine_rings;
+use crate::leaderboard::LeaderboardPlugin;
+use crate::menu::MenuPlugin;
+use crate::palette::PalettePlugin;
+use crate::particles::ParticlePlugin;
+use crate::player::read_controls;
+use crate::settings::{adjust_volume, save_settings, toggle_effects, Settings};
+use crate::sim::SimPlugin;
+use crate::stats::{record_game, Stats};
+use crate::versus::VersusPlugin;
+use crate::ui::{apply_window_settings, init_fonts, init_hud, init_ui_background, remove_hud, update_hud, set_windows};
+use crate::world::{
+    cursor_system, init_background, init_material, remove_background, setup, setup_mouse,
+};
+use bevy::input::system::exit_on_esc_system;
+use bevy::prelude::*;
+use bevy::core::{CorePlugin, FixedTimestep};
+use bevy::app::Events;
+use bevy::window::WindowResized;
+use bevy::ecs::schedule::ShouldRun;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum GameState {
+    Init,
+    Menu,
+    Settings,
+    Stats,
+    Leaderboard,
+    Game,
+    GameOver,
+}
+
+pub fn run() {
+    #[cfg(target_arch = "wasm32")]
+        console_error_panic_hook::set_once();
+
+    let options = match LaunchOptions::from_env() {
+        Ok(options) => options,
+        Err(error) => return launch_error(&error),
+    };
+    if options.headless {
+        return run_headless(&options).unwrap_or_else(|error| launch_error(&error));
+    }
+
+    let mut app = App::build();
+
+    if let Some(window) = options.window() {
+        app.insert_resource(window);
+    }
+    app.add_plugins(DefaultPlugins);
+
+    // when building for Web, use WebGL2 rendering
+    #[cfg(target_arch = "wasm32")]
+        app.add_plugin(bevy_webgl2::WebGL2Plugin);
+
+    #[cfg(feature = "online")]
+        app.add_plugin(online::OnlinePlugin);
+
+    #[cfg(feature = "debug")]
+        app.add_plugin(debug::DebugPlugin);
+
+    #[cfg(feature = "console")]
+        app.add_plugin(console::ConsolePlugin);
+
+    app.insert_resource(Settings::load())
+        .insert_resource(Stats::load())
+        .add_plugin(SimPlugin)
+        .add_plugin(ParticlePlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(GameAudioPlugin)
+        .add_plugin(PalettePlugin)
+        .add_plugin(MenuPlugin)
+        .add_plugin(LeaderboardPlugin)
+        .add_plugin(DailyPlugin)
+        .add_plugin(GhostPlugin)
+        .add_plugin(VersusPlugin);
+
+    match LaunchPlugin::new(options) {
+        Ok(launch) => {
+            app.add_plugin(launch);
+        }
+        Err(error) => return launch_error(&error),
+    }
+
+    // These need the simulation stage, so they go after SimPlugin.
+    #[cfg(all(feature = "spectate", not(target_arch = "wasm32")))]
+        app.add_plugin(spectate::SpectatePlugin);
+
+    #[cfg(all(feature = "netplay", not(target_arch = "wasm32")))]
+        match net::NetConfig::from_env() {
+            Some(Ok(config)) => {
+                app.add_plugin(net::NetPlugin { config });
+            }
+            Some(Err(error)) => error!("netplay disabled: {}", error),
+            None => {}
+        }
+
+    app.add_state(GameState::Init)
+        .add_startup_stage(
+            "init",
+            SystemStage::parallel()
+                .with_system(setup.system())
+                .with_system(init_material.system())
+                .with_system(setup_mouse.system())
+                .with_system(init_fonts.system())
+                .with_system(set_windows.system()),
+        )
+        .add_startup_stage(
+            "app_start",
+            SystemStage::parallel()
+                .with_system(enter_menu.system())
+                .with_system(init_ui_background.system()),
+        )
+        .add_system(exit_on_esc_system.system())
+        .add_system(read_controls.system())
+        .add_system(toggle_effects.system())
+        .add_system(adjust_volume.system())
+        .add_system(save_settings.system())
+        .add_system(apply_window_settings.system())
+        .add_system(cursor_system.system())
+        .add_system_set(
+            SystemSet::on_enter(GameState::Game)
+                .with_system(init_hud.system())
+                .with_system(init_background.system())
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Game)
+                .with_system(update_mine_rings.system())
+                .with_system(update_hud.system())
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::Game)
+                .with_system(record_game.system())
+                .with_system(remove_hud.system())
+                .with_system(remove_background.system()),
+        )
+        .run();
+}
+
+fn launch_error(error: &str) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        eprintln!("{}\n{}", error, USAGE);
+        std::process::exit(2);
+    }
+    #[cfg(target_arch = "wasm32")]
+        web_sys::console::error_1(&format!("{}\n{}", error, USAGE).into());
+}
+
+fn enter_menu(mut game_state: ResMut<State<GameState>>) {
+    game_state.set(GameState::Menu).unwrap();
+}
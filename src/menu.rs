@@ -0,0 +1,734 @@
+use crate::camera::CameraMode;
+use crate::daily::{DailyHistory, Modifier};
+use crate::date::Date;
+use crate::palette::{step_palette, ActivePalette, TextColorRole};
+use crate::player::{ControlScheme, ThrustModel};
+use crate::settings::{nudge_volume, Settings};
+use crate::stats::Stats;
+use crate::ui::{text_bundle, BestTime, Fonts};
+use crate::world::{Difficulty, MaterialResource};
+use crate::GameState;
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const TITLE_FONT_SIZE: f32 = 72f32;
+const BUTTON_FONT_SIZE: f32 = 28f32;
+const LINE_FONT_SIZE: f32 = 28f32;
+const BUTTON_WIDTH: f32 = 360f32;
+const BUTTON_HEIGHT: f32 = 36f32;
+const SPACING: f32 = 4f32;
+const STICK_THRESHOLD: f32 = 0.5f32;
+const DAILY_HISTORY_LINES: usize = 5;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic,
+    Daily,
+    Versus,
+    Coop,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Classic
+    }
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Classic,
+        GameMode::Daily,
+        GameMode::Versus,
+        GameMode::Coop,
+    ];
+    // Modes with a leaderboard.
+    pub const RANKED: [GameMode; 2] = [GameMode::Classic, GameMode::Daily];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Daily => "Daily",
+            GameMode::Versus => "Versus",
+            GameMode::Coop => "Co-op",
+        }
+    }
+
+    pub fn ranked(self) -> bool {
+        GameMode::RANKED.contains(&self)
+    }
+
+    // Whether a round with `alive` players left is over.
+    pub fn round_over(self, alive: usize) -> bool {
+        match self {
+            GameMode::Versus => alive <= 1,
+            _ => alive == 0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SettingRow {
+    Fullscreen,
+    Vsync,
+    Particles,
+    ScreenShake,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Palette,
+    Thrust,
+    Camera,
+    Controls,
+    Difficulty,
+    Ghost,
+    GhostEnemies,
+    VersusRounds,
+    VersusEnemies,
+    CoopPlayers,
+    CoopRevive,
+}
+
+// The settings screen shows one page of rows at a time, so it fits in a small window.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SettingsPage {
+    Video,
+    Audio,
+    Controls,
+    Gameplay,
+}
+
+impl SettingsPage {
+    const ALL: [SettingsPage; 4] = [
+        SettingsPage::Video,
+        SettingsPage::Audio,
+        SettingsPage::Controls,
+        SettingsPage::Gameplay,
+    ];
+
+    fn rows(self) -> &'static [SettingRow] {
+        match self {
+            SettingsPage::Video => &[
+                SettingRow::Fullscreen,
+                SettingRow::Vsync,
+                SettingRow::Particles,
+                SettingRow::ScreenShake,
+                SettingRow::Palette,
+                SettingRow::Camera,
+            ],
+            SettingsPage::Audio => &[
+                SettingRow::MasterVolume,
+                SettingRow::MusicVolume,
+                SettingRow::SfxVolume,
+            ],
+            SettingsPage::Controls => &[SettingRow::Controls, SettingRow::Thrust],
+            SettingsPage::Gameplay => &[
+                SettingRow::Difficulty,
+                SettingRow::Ghost,
+                SettingRow::GhostEnemies,
+                SettingRow::VersusRounds,
+                SettingRow::VersusEnemies,
+                SettingRow::CoopPlayers,
+                SettingRow::CoopRevive,
+            ],
+        }
+    }
+}
+
+// Versus matches are best of one of these.
+pub const VERSUS_ROUNDS: [u32; 4] = [1, 3, 5, 7];
+pub const COOP_PLAYERS: [u8; 3] = [2, 3, 4];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MenuAction {
+    Play,
+    Mode,
+    Settings,
+    Stats,
+    Leaderboard,
+    Quit,
+    Back,
+    SettingsPage,
+    Setting(SettingRow),
+    BoardMode,
+    BoardDifficulty,
+    BoardSort,
+    SaveScore,
+    Continue,
+    NextRound,
+    Rematch,
+}
+
+impl MenuAction {
+    // Actions that can be stepped backwards and forwards with left and right.
+    fn adjustable(self) -> bool {
+        matches!(
+            self,
+            MenuAction::Mode
+                | MenuAction::SettingsPage
+                | MenuAction::Setting(_)
+                | MenuAction::BoardMode
+                | MenuAction::BoardDifficulty
+                | MenuAction::BoardSort
+        )
+    }
+}
+
+// Sent when a focused button is activated, `step` is -1 when stepping a value backwards.
+pub struct MenuEvent {
+    pub action: MenuAction,
+    pub step: i32,
+}
+
+pub struct MenuButton {
+    pub action: MenuAction,
+    pub index: usize,
+}
+
+pub struct MenuScreenMarker;
+
+pub struct MenuFocus(pub usize);
+
+#[derive(Default)]
+pub struct ConnectedGamepads(pub Vec<Gamepad>);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(MenuFocus(0))
+            .insert_resource(SettingsPage::Video)
+            .init_resource::<ConnectedGamepads>()
+            .add_event::<MenuEvent>()
+            .add_system(track_gamepads.system())
+            .add_system(menu_navigation.system())
+            .add_system(handle_menu_events.system())
+            .add_system(turn_settings_page.system())
+            .add_system(update_button_labels.system())
+            .add_system(update_button_focus.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Menu).with_system(spawn_main_menu.system()),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(despawn_menu.system()))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Settings).with_system(spawn_settings_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Settings).with_system(despawn_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Stats).with_system(spawn_stats_screen.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Stats).with_system(despawn_menu.system()),
+            );
+    }
+}
+
+pub fn cycle<T: Copy + PartialEq>(values: &[T], current: T, step: i32) -> T {
+    let count = values.len() as i32;
+    let index = values
+        .iter()
+        .position(|value| *value == current)
+        .unwrap_or(0) as i32;
+    values[(index + step).rem_euclid(count) as usize]
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn percent(value: f32) -> String {
+    format!("{}%", (value * 100f32).round() as i32)
+}
+
+// Leaderboard buttons are labelled by the leaderboard module, which owns their state.
+fn button_label(
+    action: MenuAction,
+    settings: &Settings,
+    palette: &ActivePalette,
+    page: SettingsPage,
+) -> Option<String> {
+    let label = match action {
+        MenuAction::Play => "Play".to_string(),
+        MenuAction::Mode => match settings.game_mode {
+            GameMode::Daily => format!(
+                "Mode: Daily ({})",
+                Modifier::for_date(Date::today()).name()
+            ),
+            GameMode::Versus => format!("Mode: Versus (best of {})", settings.versus_rounds),
+            GameMode::Coop => format!("Mode: Co-op ({} players)", settings.coop_players),
+            mode => format!("Mode: {}", mode.name()),
+        },
+        MenuAction::Settings => "Settings".to_string(),
+        MenuAction::Stats => "Stats".to_string(),
+        MenuAction::Leaderboard => "Leaderboard".to_string(),
+        MenuAction::Quit => "Quit".to_string(),
+        MenuAction::Back => "Back".to_string(),
+        MenuAction::SettingsPage => format!("Page: {:?}", page),
+        MenuAction::SaveScore => "Save".to_string(),
+        MenuAction::Continue => "Continue".to_string(),
+        MenuAction::NextRound => "Next round".to_string(),
+        MenuAction::Rematch => "Rematch".to_string(),
+        MenuAction::BoardMode | MenuAction::BoardDifficulty | MenuAction::BoardSort => return None,
+        MenuAction::Setting(row) => match row {
+            SettingRow::Fullscreen => format!("Fullscreen: {}", on_off(settings.fullscreen)),
+            SettingRow::Vsync => format!("VSync: {}", on_off(settings.vsync)),
+            SettingRow::Particles => format!("Particles: {}", on_off(settings.particles)),
+            SettingRow::ScreenShake => format!("Screen shake: {}", on_off(settings.screen_shake)),
+            SettingRow::MasterVolume => format!("Volume: {}", percent(settings.master_volume)),
+            SettingRow::MusicVolume => format!("Music: {}", percent(settings.music_volume)),
+            SettingRow::SfxVolume => format!("Effects: {}", percent(settings.sfx_volume)),
+            SettingRow::Palette => format!("Palette: {}", palette.palette.name),
+            SettingRow::Thrust => format!("Thrust: {:?}", settings.thrust_model),
+            SettingRow::Camera => format!("Camera: {:?}", settings.camera_mode),
+            SettingRow::Controls => format!("Controls: {:?}", settings.control_scheme),
+            SettingRow::Difficulty => format!("Difficulty: {:?}", settings.difficulty),
+            SettingRow::Ghost => format!("Ghost: {}", on_off(settings.ghost)),
+            SettingRow::GhostEnemies => {
+                format!("Ghost enemies: {}", on_off(settings.ghost_enemies))
+            }
+            SettingRow::VersusRounds => format!("Versus: best of {}", settings.versus_rounds),
+            SettingRow::VersusEnemies => {
+                format!("Versus enemies: {}", on_off(settings.versus_enemies))
+            }
+            SettingRow::CoopPlayers => format!("Co-op players: {}", settings.coop_players),
+            SettingRow::CoopRevive => format!("Co-op revive: {}", on_off(settings.coop_revive)),
+        },
+    };
+    Some(label)
+}
+
+pub fn menu_root(materials: &MaterialResource) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100f32), Val::Percent(100f32)),
+            position_type: PositionType::Absolute,
+            flex_direction: FlexDirection::ColumnReverse,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: materials.panel_material.clone(),
+        ..Default::default()
+    }
+}
+
+pub fn spawn_buttons(
+    parent: &mut ChildBuilder,
+    actions: &[MenuAction],
+    fonts: &Fonts,
+    materials: &MaterialResource,
+    palette: &ActivePalette,
+) {
+    for (index, action) in actions.iter().enumerate() {
+        parent
+            .spawn_bundle(ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(BUTTON_WIDTH), Val::Px(BUTTON_HEIGHT)),
+                    margin: Rect::all(Val::Px(SPACING)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                material: materials.button_material.clone(),
+                ..Default::default()
+            })
+            .insert(MenuButton {
+                action: *action,
+                index,
+            })
+            .with_children(|button| {
+                button
+                    .spawn_bundle(text_bundle(
+                        String::new(),
+                        fonts,
+                        BUTTON_FONT_SIZE,
+                        palette.palette.text,
+                        0f32,
+                    ))
+                    .insert(TextColorRole::Text);
+            });
+    }
+}
+
+pub fn spawn_title(parent: &mut ChildBuilder, title: &str, fonts: &Fonts, palette: &ActivePalette) {
+    parent
+        .spawn_bundle(text_bundle(
+            title.to_string(),
+            fonts,
+            TITLE_FONT_SIZE,
+            palette.palette.text,
+            SPACING * 2f32,
+        ))
+        .insert(TextColorRole::Text);
+}
+
+pub fn spawn_main_menu(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    materials: Res<MaterialResource>,
+    palette: Res<ActivePalette>,
+) {
+    let mut actions = vec![
+        MenuAction::Play,
+        MenuAction::Mode,
+        MenuAction::Settings,
+        MenuAction::Stats,
+        MenuAction::Leaderboard,
+    ];
+    // Browsers do not let a page close itself.
+    if cfg!(not(target_arch = "wasm32")) {
+        actions.push(MenuAction::Quit);
+    }
+    commands
+        .spawn_bundle(menu_root(&materials))
+        .insert(MenuScreenMarker)
+        .with_children(|parent| {
+            spawn_title(parent, "switch", &fonts, &palette);
+            spawn_buttons(parent, &actions, &fonts, &materials, &palette);
+        });
+}
+
+fn spawn_settings_page(
+    commands: &mut Commands,
+    page: SettingsPage,
+    fonts: &Fonts,
+    materials: &MaterialResource,
+    palette: &ActivePalette,
+) {
+    let mut actions = vec![MenuAction::SettingsPage];
+    actions.extend(page.rows().iter().map(|row| MenuAction::Setting(*row)));
+    actions.push(MenuAction::Back);
+    commands
+        .spawn_bundle(menu_root(&materials))
+        .insert(MenuScreenMarker)
+        .with_children(|parent| {
+            spawn_title(parent, "Settings", fonts, palette);
+            spawn_buttons(parent, &actions, fonts, materials, palette);
+        });
+}
+
+pub fn spawn_settings_menu(
+    mut commands: Commands,
+    page: Res<SettingsPage>,
+    fonts: Res<Fonts>,
+    materials: Res<MaterialResource>,
+    palette: Res<ActivePalette>,
+) {
+    spawn_settings_page(&mut commands, *page, &fonts, &materials, &palette);
+}
+
+// Swaps the rows for the next page, keeping the focus on the page button.
+pub fn turn_settings_page(
+    mut commands: Commands,
+    mut events: EventReader<MenuEvent>,
+    mut page: ResMut<SettingsPage>,
+    fonts: Res<Fonts>,
+    materials: Res<MaterialResource>,
+    palette: Res<ActivePalette>,
+    screens: Query<Entity, With<MenuScreenMarker>>,
+) {
+    for event in events.iter() {
+        if event.action != MenuAction::SettingsPage {
+            continue;
+        }
+        *page = cycle(&SettingsPage::ALL, *page, event.step);
+        for entity in screens.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        spawn_settings_page(&mut commands, *page, &fonts, &materials, &palette);
+    }
+}
+
+pub fn spawn_stats_screen(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    materials: Res<MaterialResource>,
+    palette: Res<ActivePalette>,
+    stats: Res<Stats>,
+    best_time: Res<BestTime>,
+    history: Res<DailyHistory>,
+) {
+    let mut lines = vec![
+        format!("Best time: {:.1}", best_time.0),
+        format!("Best score: {}", stats.best_score),
+        format!("Games played: {}", stats.games_played),
+        format!("Time played: {:.0}s", stats.total_time),
+        format!("Enemies destroyed: {}", stats.kills),
+        format!(
+            "Daily streak: {} (best {})",
+            history.streak(Date::today()),
+            history.best_streak()
+        ),
+    ];
+    for result in history.recent(DAILY_HISTORY_LINES) {
+        lines.push(format!(
+            "{}  {:<20} {:>6.1} {:>4}",
+            result.date,
+            result.modifier.name(),
+            result.time,
+            result.score
+        ));
+    }
+    commands
+        .spawn_bundle(menu_root(&materials))
+        .insert(MenuScreenMarker)
+        .with_children(|parent| {
+            spawn_title(parent, "Stats", &fonts, &palette);
+            for line in lines.iter() {
+                parent
+                    .spawn_bundle(text_bundle(
+                        line.clone(),
+                        &fonts,
+                        LINE_FONT_SIZE,
+                        palette.palette.text,
+                        SPACING,
+                    ))
+                    .insert(TextColorRole::Text);
+            }
+            spawn_buttons(parent, &[MenuAction::Back], &fonts, &materials, &palette);
+        });
+}
+
+pub fn despawn_menu(
+    mut commands: Commands,
+    mut focus: ResMut<MenuFocus>,
+    screens: Query<Entity, With<MenuScreenMarker>>,
+) {
+    for entity in screens.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    focus.0 = 0;
+}
+
+pub fn track_gamepads(
+    mut events: EventReader<GamepadEvent>,
+    mut gamepads: ResMut<ConnectedGamepads>,
+) {
+    for GamepadEvent(gamepad, event) in events.iter() {
+        match event {
+            GamepadEventType::Connected => gamepads.0.push(*gamepad),
+            GamepadEventType::Disconnected => gamepads.0.retain(|known| known != gamepad),
+            _ => {}
+        }
+    }
+}
+
+pub fn menu_navigation(
+    state: Res<State<GameState>>,
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<ConnectedGamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut focus: ResMut<MenuFocus>,
+    mut pressed: Local<Option<usize>>,
+    mut stick_held: Local<bool>,
+    mut events: EventWriter<MenuEvent>,
+    buttons: Query<&MenuButton>,
+    interactions: Query<(&MenuButton, &Interaction), Changed<Interaction>>,
+) {
+    let count = buttons.iter().count();
+    if count == 0 {
+        return;
+    }
+    let focused = buttons.iter().find(|button| button.index == focus.0);
+
+    // Buttons trigger on release like the old click to play prompt, so the click that starts a
+    // game does not also fire the player's first switch.
+    for (button, interaction) in interactions.iter() {
+        match interaction {
+            Interaction::Clicked => *pressed = Some(button.index),
+            Interaction::Hovered => {
+                focus.0 = button.index;
+                if *pressed == Some(button.index) {
+                    events.send(MenuEvent {
+                        action: button.action,
+                        step: 1,
+                    });
+                }
+                *pressed = None;
+            }
+            Interaction::None => {
+                if *pressed == Some(button.index) {
+                    *pressed = None;
+                }
+            }
+        }
+    }
+
+    // Letters, space and backspace type into the name field on the game over screen.
+    let typing = *state.current() == GameState::GameOver;
+    let letter = |key: KeyCode| !typing && keys.just_pressed(key);
+    let mut vertical = 0;
+    let mut horizontal = 0;
+    let mut activate = false;
+    let mut back = false;
+    if keys.just_pressed(KeyCode::Up) || letter(KeyCode::W) {
+        vertical -= 1;
+    }
+    if keys.just_pressed(KeyCode::Down) || letter(KeyCode::S) {
+        vertical += 1;
+    }
+    if keys.just_pressed(KeyCode::Left) || letter(KeyCode::A) {
+        horizontal -= 1;
+    }
+    if keys.just_pressed(KeyCode::Right) || letter(KeyCode::D) {
+        horizontal += 1;
+    }
+    activate |= keys.just_pressed(KeyCode::Return) || letter(KeyCode::Space);
+    back |= letter(KeyCode::Back);
+    for button in gamepad_buttons.get_just_pressed() {
+        match button.1 {
+            GamepadButtonType::DPadUp => vertical -= 1,
+            GamepadButtonType::DPadDown => vertical += 1,
+            GamepadButtonType::DPadLeft => horizontal -= 1,
+            GamepadButtonType::DPadRight => horizontal += 1,
+            GamepadButtonType::South | GamepadButtonType::Start => activate = true,
+            GamepadButtonType::East => back = true,
+            _ => {}
+        }
+    }
+    let stick: f32 = gamepads
+        .0
+        .iter()
+        .filter_map(|gamepad| gamepad_axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickY)))
+        .sum();
+    if stick.abs() > STICK_THRESHOLD {
+        if !*stick_held {
+            vertical += if stick > 0f32 { -1 } else { 1 };
+        }
+        *stick_held = true;
+    } else {
+        *stick_held = false;
+    }
+
+    if vertical != 0 {
+        focus.0 = (focus.0 as i32 + vertical).rem_euclid(count as i32) as usize;
+    }
+    if let Some(button) = focused {
+        if activate {
+            events.send(MenuEvent {
+                action: button.action,
+                step: 1,
+            });
+        } else if horizontal != 0 && button.action.adjustable() {
+            events.send(MenuEvent {
+                action: button.action,
+                step: horizontal,
+            });
+        }
+    }
+    if back {
+        events.send(MenuEvent {
+            action: MenuAction::Back,
+            step: 1,
+        });
+    }
+}
+
+pub fn handle_menu_events(
+    mut events: EventReader<MenuEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut settings: ResMut<Settings>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for event in events.iter() {
+        match event.action {
+            MenuAction::Play => state.set(GameState::Game).unwrap_or(()),
+            MenuAction::Mode => {
+                settings.game_mode = cycle(&GameMode::ALL, settings.game_mode, event.step)
+            }
+            MenuAction::Settings => state.set(GameState::Settings).unwrap_or(()),
+            MenuAction::Stats => state.set(GameState::Stats).unwrap_or(()),
+            MenuAction::Leaderboard => state.set(GameState::Leaderboard).unwrap_or(()),
+            MenuAction::Quit => exit.send(AppExit),
+            MenuAction::Back => match state.current() {
+                GameState::Settings | GameState::Stats | GameState::Leaderboard => {
+                    state.set(GameState::Menu).unwrap_or(())
+                }
+                _ => {}
+            },
+            MenuAction::Setting(row) => apply_setting(&mut settings, row, event.step),
+            _ => {}
+        }
+    }
+}
+
+fn apply_setting(settings: &mut Settings, row: SettingRow, step: i32) {
+    match row {
+        SettingRow::Fullscreen => settings.fullscreen = !settings.fullscreen,
+        SettingRow::Vsync => settings.vsync = !settings.vsync,
+        SettingRow::Particles => settings.particles = !settings.particles,
+        SettingRow::ScreenShake => settings.screen_shake = !settings.screen_shake,
+        SettingRow::MasterVolume => nudge_volume(&mut settings.master_volume, step),
+        SettingRow::MusicVolume => nudge_volume(&mut settings.music_volume, step),
+        SettingRow::SfxVolume => nudge_volume(&mut settings.sfx_volume, step),
+        SettingRow::Palette => step_palette(settings, step),
+        SettingRow::Thrust => {
+            settings.thrust_model = cycle(&ThrustModel::ALL, settings.thrust_model, step)
+        }
+        SettingRow::Camera => {
+            settings.camera_mode = cycle(&CameraMode::ALL, settings.camera_mode, step)
+        }
+        SettingRow::Controls => {
+            settings.control_scheme = cycle(&ControlScheme::ALL, settings.control_scheme, step)
+        }
+        SettingRow::Difficulty => {
+            settings.difficulty = cycle(&Difficulty::ALL, settings.difficulty, step)
+        }
+        SettingRow::Ghost => settings.ghost = !settings.ghost,
+        SettingRow::GhostEnemies => settings.ghost_enemies = !settings.ghost_enemies,
+        SettingRow::VersusRounds => {
+            settings.versus_rounds = cycle(&VERSUS_ROUNDS, settings.versus_rounds, step)
+        }
+        SettingRow::VersusEnemies => settings.versus_enemies = !settings.versus_enemies,
+        SettingRow::CoopPlayers => {
+            settings.coop_players = cycle(&COOP_PLAYERS, settings.coop_players, step)
+        }
+        SettingRow::CoopRevive => settings.coop_revive = !settings.coop_revive,
+    }
+}
+
+pub fn update_button_labels(
+    settings: Res<Settings>,
+    palette: Res<ActivePalette>,
+    page: Res<SettingsPage>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (button, children) in buttons.iter() {
+        let label = match button_label(button.action, &settings, &palette, *page) {
+            Some(label) => label,
+            None => continue,
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                if text.sections[0].value != label {
+                    text.sections[0].value = label.clone();
+                }
+            }
+        }
+    }
+}
+
+pub fn update_button_focus(
+    focus: Res<MenuFocus>,
+    materials: Res<MaterialResource>,
+    mut buttons: Query<(&MenuButton, &mut Handle<ColorMaterial>)>,
+) {
+    for (button, mut material) in buttons.iter_mut() {
+        let wanted = if button.index == focus.0 {
+            &materials.button_focused_material
+        } else {
+            &materials.button_material
+        };
+        if *material != *wanted {
+            *material = wanted.clone();
+        }
+    }
+}
@@ -0,0 +1,452 @@
+use crate::palette::{ActivePalette, TextColorRole};
+use crate::player::PlayerSlot;
+use crate::rng::{GameRng, RunSeed};
+use crate::sim::{InputQueue, NetInput, SimClock, SimConfig, TickGate, SIM_STAGE, START_RUN};
+use crate::ui::{text_bundle, Fonts};
+use crate::world::{CharType, ObjectMarker};
+use crate::GameState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+// Peers only exchange switches. Both run the same simulation and a tick is only stepped once the
+// switches of both players for it are known, so local switches are scheduled `INPUT_DELAY` ticks
+// ahead to hide the round trip.
+const INPUT_DELAY: u64 = 4;
+const MAX_TICKS_PER_PACKET: u64 = 64;
+const HASH_INTERVAL: u64 = 15;
+const HASHES_KEPT: usize = 64;
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PACKET_SIZE: usize = 8192;
+const STATUS_FONT_SIZE: f32 = 20f32;
+const STATUS_MARGIN: f32 = 8f32;
+
+// Read from `SWITCH_NET_BIND`, `SWITCH_NET_PEER` and `SWITCH_NET_SLOT`. Slot 0 hosts and picks
+// the seed, both peers need the same settings.
+pub struct NetConfig {
+    pub bind: SocketAddr,
+    pub peer: SocketAddr,
+    pub slot: u8,
+}
+
+impl NetConfig {
+    pub fn from_env() -> Option<Result<Self, String>> {
+        let peer = std::env::var("SWITCH_NET_PEER").ok()?;
+        Some(Self::parse(
+            &std::env::var("SWITCH_NET_BIND").unwrap_or_else(|_| "0.0.0.0:0".to_string()),
+            &peer,
+            &std::env::var("SWITCH_NET_SLOT").unwrap_or_else(|_| "0".to_string()),
+        ))
+    }
+
+    fn parse(bind: &str, peer: &str, slot: &str) -> Result<Self, String> {
+        let slot = slot
+            .parse()
+            .ok()
+            .filter(|slot| *slot < 2)
+            .ok_or_else(|| format!("invalid slot {}", slot))?;
+        Ok(NetConfig {
+            bind: bind
+                .parse()
+                .map_err(|_| format!("invalid bind address {}", bind))?,
+            peer: peer
+                .parse()
+                .map_err(|_| format!("invalid peer address {}", peer))?,
+            slot,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum Packet {
+    Start {
+        run: u32,
+        config: SimConfig,
+        seed: u64,
+    },
+    // Switches of the sender for every tick from `from`, `ack` is how many of the receiver's
+    // ticks the sender has.
+    Frame {
+        run: u32,
+        ack: u64,
+        from: u64,
+        inputs: Vec<Vec<[f32; 2]>>,
+        hashes: Vec<(u64, u64)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetStatus {
+    Idle,
+    Waiting,
+    Running,
+    Unsupported,
+    Mismatch,
+    Desync(u64),
+    TimedOut,
+}
+
+impl NetStatus {
+    fn label(&self) -> String {
+        match self {
+            NetStatus::Idle => String::new(),
+            NetStatus::Waiting => "Waiting for peer...".to_string(),
+            NetStatus::Running => "Connected".to_string(),
+            NetStatus::Unsupported => "Netplay needs a two player mode".to_string(),
+            NetStatus::Mismatch => "Settings differ from the host".to_string(),
+            NetStatus::Desync(tick) => format!("Desynced at tick {}", tick),
+            NetStatus::TimedOut => "Peer timed out".to_string(),
+        }
+    }
+}
+
+pub struct NetSession {
+    socket: UdpSocket,
+    peer: SocketAddr,
+    slot: u8,
+    active: bool,
+    run: u32,
+    start: Option<(SimConfig, u64)>,
+    local: BTreeMap<u64, Vec<[f32; 2]>>,
+    remote: BTreeMap<u64, Vec<[f32; 2]>>,
+    // Ticks below these are known contiguously, sent to the simulation, and acknowledged by the
+    // peer.
+    remote_known: u64,
+    released: u64,
+    peer_ack: u64,
+    local_hashes: BTreeMap<u64, u64>,
+    remote_hashes: BTreeMap<u64, u64>,
+    last_heard: Instant,
+    pub status: NetStatus,
+}
+
+impl NetSession {
+    pub fn open(config: &NetConfig) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(config.bind)?;
+        socket.set_nonblocking(true)?;
+        Ok(NetSession {
+            socket,
+            peer: config.peer,
+            slot: config.slot,
+            active: false,
+            run: 0,
+            start: None,
+            local: BTreeMap::new(),
+            remote: BTreeMap::new(),
+            remote_known: 0,
+            released: 0,
+            peer_ack: 0,
+            local_hashes: BTreeMap::new(),
+            remote_hashes: BTreeMap::new(),
+            last_heard: Instant::now(),
+            status: NetStatus::Idle,
+        })
+    }
+
+    fn host(&self) -> bool {
+        self.slot == 0
+    }
+
+    fn send(&self, packet: &Packet) {
+        if let Ok(bytes) = serde_json::to_vec(packet) {
+            self.socket.send_to(&bytes, self.peer).unwrap_or(0);
+        }
+    }
+
+    fn reset(&mut self, run: u32) {
+        self.active = true;
+        self.run = run;
+        self.start = None;
+        self.local.clear();
+        self.remote.clear();
+        self.remote_known = 0;
+        self.released = 0;
+        self.peer_ack = 0;
+        self.local_hashes.clear();
+        self.remote_hashes.clear();
+        self.last_heard = Instant::now();
+        self.status = NetStatus::Waiting;
+    }
+
+    fn check_hashes(&mut self) {
+        for (tick, hash) in self.remote_hashes.iter() {
+            if let Some(local) = self.local_hashes.get(tick) {
+                if local != hash && !matches!(self.status, NetStatus::Desync(_)) {
+                    error!("netplay desync at tick {}", tick);
+                    self.status = NetStatus::Desync(*tick);
+                }
+            }
+        }
+        let oldest = self.local_hashes.keys().next().copied().unwrap_or(0);
+        self.remote_hashes.retain(|tick, _| *tick >= oldest);
+    }
+}
+
+pub struct NetStatusMarker;
+
+pub struct NetPlugin {
+    pub config: NetConfig,
+}
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let session = match NetSession::open(&self.config) {
+            Ok(session) => session,
+            Err(error) => {
+                error!("could not open the netplay socket: {}", error);
+                return;
+            }
+        };
+        app.insert_resource(session)
+            .insert_resource(NetInput {
+                local_slot: Some(self.config.slot),
+                outgoing: Vec::new(),
+            })
+            .add_system(exchange_inputs.system())
+            // Added after the simulation's own systems, so it hashes the end of every tick.
+            .add_system_to_stage(SIM_STAGE, hash_state.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game)
+                    .with_system(begin_net_run.system().after(START_RUN))
+                    .with_system(spawn_status_line.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Game).with_system(update_status_line.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Game).with_system(remove_status_line.system()),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(end_session.system()));
+    }
+}
+
+// The host sends its seed and settings, the other peer holds the simulation until they arrive.
+pub fn begin_net_run(
+    config: Res<SimConfig>,
+    seed: Res<RunSeed>,
+    mut session: ResMut<NetSession>,
+    mut gate: ResMut<TickGate>,
+) {
+    gate.0 = Some(0);
+    let run = session.run + 1;
+    session.reset(run);
+    if config.players != 2 {
+        session.status = NetStatus::Unsupported;
+        return;
+    }
+    if session.host() {
+        session.start = Some((*config, seed.0));
+    }
+}
+
+fn receive(session: &mut NetSession, config: &SimConfig, seed: &mut RunSeed, rng: &mut GameRng) {
+    let mut buffer = [0u8; PACKET_SIZE];
+    while let Ok((size, from)) = session.socket.recv_from(&mut buffer) {
+        if from != session.peer {
+            continue;
+        }
+        let packet: Packet = match serde_json::from_slice(&buffer[..size]) {
+            Ok(packet) => packet,
+            Err(_) => continue,
+        };
+        session.last_heard = Instant::now();
+        match packet {
+            Packet::Start {
+                run,
+                config: host_config,
+                seed: host_seed,
+            } => {
+                if session.host() || session.start.is_some() {
+                    continue;
+                }
+                session.run = run;
+                if host_config != *config {
+                    session.status = NetStatus::Mismatch;
+                    continue;
+                }
+                seed.0 = host_seed;
+                *rng = GameRng::new(host_seed);
+                session.start = Some((host_config, host_seed));
+            }
+            Packet::Frame {
+                run,
+                ack,
+                from,
+                inputs,
+                hashes,
+            } => {
+                if session.start.is_none() || run != session.run {
+                    continue;
+                }
+                session.peer_ack = session.peer_ack.max(ack);
+                for (offset, aims) in inputs.into_iter().enumerate() {
+                    let tick = from + offset as u64;
+                    if tick >= session.remote_known {
+                        session.remote.entry(tick).or_insert(aims);
+                    }
+                }
+                while session.remote.contains_key(&session.remote_known) {
+                    session.remote_known += 1;
+                }
+                session.remote_hashes.extend(hashes);
+            }
+        }
+    }
+}
+
+pub fn exchange_inputs(
+    clock: Res<SimClock>,
+    config: Res<SimConfig>,
+    mut seed: ResMut<RunSeed>,
+    mut rng: ResMut<GameRng>,
+    mut net: ResMut<NetInput>,
+    mut queue: ResMut<InputQueue>,
+    mut gate: ResMut<TickGate>,
+    mut session: ResMut<NetSession>,
+) {
+    if !session.active {
+        return;
+    }
+    receive(&mut session, &config, &mut seed, &mut rng);
+    if session.status == NetStatus::Running && session.last_heard.elapsed() > TIMEOUT {
+        session.status = NetStatus::TimedOut;
+    }
+    let (start_config, start_seed) = match session.start {
+        Some(start) => start,
+        None => return,
+    };
+    if session.host() && session.remote_known == 0 {
+        session.send(&Packet::Start {
+            run: session.run,
+            config: start_config,
+            seed: start_seed,
+        });
+    }
+
+    // Ticks before `sealed` can no longer get local switches and are safe to send.
+    let sealed = clock.tick + INPUT_DELAY;
+    for command in net.outgoing.drain(..) {
+        session.local.entry(sealed).or_default().push(command.aim);
+    }
+    let from = session
+        .peer_ack
+        .max(sealed.saturating_sub(MAX_TICKS_PER_PACKET));
+    let inputs = (from..sealed)
+        .map(|tick| session.local.get(&tick).cloned().unwrap_or_default())
+        .collect();
+    let hashes = session
+        .local_hashes
+        .iter()
+        .map(|(tick, hash)| (*tick, *hash))
+        .collect();
+    session.send(&Packet::Frame {
+        run: session.run,
+        ack: session.remote_known,
+        from,
+        inputs,
+        hashes,
+    });
+
+    // Both players' switches go into the queue in slot order, so both peers apply them alike.
+    let local_slot = session.slot;
+    while session.released < sealed && session.released < session.remote_known {
+        let tick = session.released;
+        let local = session.local.get(&tick).cloned().unwrap_or_default();
+        let remote = session.remote.remove(&tick).unwrap_or_default();
+        let (first, second) = if local_slot == 0 {
+            (local, remote)
+        } else {
+            (remote, local)
+        };
+        for (slot, aims) in [(0u8, first), (1u8, second)].iter() {
+            for aim in aims.iter() {
+                queue.push(tick, *slot, Vec2::from(*aim));
+            }
+        }
+        session.released += 1;
+    }
+    // Local switches are kept until the peer has them and they went into the queue.
+    let kept = session.peer_ack.min(session.released);
+    session.local.retain(|tick, _| *tick >= kept);
+    if session.status == NetStatus::Waiting && session.remote_known > 0 {
+        session.status = NetStatus::Running;
+    }
+    gate.0 = Some(session.released);
+    session.check_hashes();
+}
+
+fn mix(hash: u64, value: u64) -> u64 {
+    (hash ^ value).wrapping_mul(0x100000001b3)
+}
+
+// Sums a hash of every object so the result does not depend on query order.
+pub fn hash_state(
+    clock: Res<SimClock>,
+    mut session: ResMut<NetSession>,
+    objects: Query<(&Transform, &CharType, &ObjectMarker, Option<&PlayerSlot>)>,
+) {
+    // The clock has already moved on to the next tick.
+    let tick = clock.tick - 1;
+    if !session.active || tick % HASH_INTERVAL != 0 {
+        return;
+    }
+    let hash = objects
+        .iter()
+        .fold(0u64, |total, (transform, char_type, team, slot)| {
+            let mut hash = 0xcbf29ce484222325;
+            hash = mix(hash, transform.translation.x.to_bits() as u64);
+            hash = mix(hash, transform.translation.y.to_bits() as u64);
+            hash = mix(hash, *char_type as u64);
+            hash = mix(hash, team.0 as u64);
+            hash = mix(hash, slot.map_or(u64::MAX, |slot| slot.0 as u64));
+            total.wrapping_add(hash)
+        });
+    session.local_hashes.insert(tick, hash);
+    while session.local_hashes.len() > HASHES_KEPT {
+        let oldest = *session.local_hashes.keys().next().unwrap();
+        session.local_hashes.remove(&oldest);
+    }
+}
+
+pub fn end_session(mut session: ResMut<NetSession>, mut gate: ResMut<TickGate>) {
+    session.active = false;
+    session.status = NetStatus::Idle;
+    gate.0 = None;
+}
+
+fn spawn_status_line(mut commands: Commands, fonts: Res<Fonts>, palette: Res<ActivePalette>) {
+    let mut line = text_bundle(
+        String::new(),
+        &fonts,
+        STATUS_FONT_SIZE,
+        palette.palette.text,
+        0f32,
+    );
+    line.style.position_type = PositionType::Absolute;
+    line.style.position = Rect {
+        bottom: Val::Px(STATUS_MARGIN),
+        left: Val::Px(STATUS_MARGIN),
+        ..Default::default()
+    };
+    commands
+        .spawn_bundle(line)
+        .insert(TextColorRole::Text)
+        .insert(NetStatusMarker);
+}
+
+fn update_status_line(session: Res<NetSession>, mut line: Query<&mut Text, With<NetStatusMarker>>) {
+    if let Ok(mut text) = line.single_mut() {
+        let label = session.status.label();
+        if text.sections[0].value != label {
+            text.sections[0].value = label;
+        }
+    }
+}
+
+fn remove_status_line(mut commands: Commands, line: Query<Entity, With<NetStatusMarker>>) {
+    for entity in line.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,213 @@
+use crate::leaderboard::{entry_name, LastRun, NameEntry};
+use crate::menu::{MenuAction, MenuEvent, MenuScreenMarker};
+use crate::palette::{ActivePalette, TextColorRole};
+use crate::protocol::{Submission, Verdict, PROTOCOL_VERSION, SUBMIT_PATH};
+use crate::ui::{text_bundle, Fonts};
+use crate::GameState;
+use bevy::prelude::*;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+const DEFAULT_SERVER: &str = "http://127.0.0.1:7878";
+const STATUS_FONT_SIZE: f32 = 20f32;
+const STATUS_MARGIN: f32 = 8f32;
+
+type Response = Result<Verdict, String>;
+
+pub enum OnlineStatus {
+    Idle,
+    Pending,
+    Accepted,
+    Rejected(String),
+    Failed(String),
+}
+
+impl OnlineStatus {
+    fn label(&self) -> String {
+        match self {
+            OnlineStatus::Idle => String::new(),
+            OnlineStatus::Pending => "Submitting online...".to_string(),
+            OnlineStatus::Accepted => "Verified online".to_string(),
+            OnlineStatus::Rejected(reason) => format!("Rejected online: {}", reason),
+            OnlineStatus::Failed(error) => format!("Could not submit: {}", error),
+        }
+    }
+}
+
+// Requests finish on another thread natively and in a future on the web, either way the
+// verdict comes back through this channel.
+struct Responses {
+    sender: Mutex<Sender<Response>>,
+    receiver: Mutex<Receiver<Response>>,
+}
+
+pub struct OnlineStatusMarker;
+
+pub struct OnlinePlugin;
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let (sender, receiver) = channel();
+        app.insert_resource(Responses {
+            sender: Mutex::new(sender),
+            receiver: Mutex::new(receiver),
+        })
+        .insert_resource(OnlineStatus::Idle)
+        .add_system(submit_runs.system())
+        .add_system(receive_verdicts.system())
+        .add_system_set(
+            SystemSet::on_enter(GameState::Leaderboard).with_system(spawn_status_line.system()),
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Leaderboard).with_system(update_status_line.system()),
+        );
+    }
+}
+
+pub fn server_url() -> String {
+    backend::configured_url()
+        .or_else(|| option_env!("SWITCH_LEADERBOARD_URL").map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_SERVER.to_string())
+}
+
+fn submit_runs(
+    mut events: EventReader<MenuEvent>,
+    last_run: Res<LastRun>,
+    name: Res<NameEntry>,
+    responses: Res<Responses>,
+    mut status: ResMut<OnlineStatus>,
+) {
+    for event in events.iter() {
+        if event.action != MenuAction::SaveScore || !last_run.qualifies {
+            continue;
+        }
+        let submission = Submission {
+            version: PROTOCOL_VERSION,
+            name: entry_name(&name),
+            replay: last_run.recording.clone(),
+            claimed_time: last_run.time,
+            claimed_score: last_run.score,
+        };
+        match serde_json::to_string(&submission) {
+            Ok(body) => {
+                let url = format!("{}{}", server_url().trim_end_matches('/'), SUBMIT_PATH);
+                let sender = responses.sender.lock().unwrap().clone();
+                backend::post(url, body, sender);
+                *status = OnlineStatus::Pending;
+            }
+            Err(error) => *status = OnlineStatus::Failed(error.to_string()),
+        }
+    }
+}
+
+fn receive_verdicts(responses: Res<Responses>, mut status: ResMut<OnlineStatus>) {
+    let receiver = responses.receiver.lock().unwrap();
+    while let Ok(response) = receiver.try_recv() {
+        *status = match response {
+            Ok(verdict) if verdict.accepted => OnlineStatus::Accepted,
+            Ok(verdict) => OnlineStatus::Rejected(verdict.reason.unwrap_or_default()),
+            Err(error) => OnlineStatus::Failed(error),
+        };
+    }
+}
+
+fn spawn_status_line(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    palette: Res<ActivePalette>,
+    status: Res<OnlineStatus>,
+) {
+    let mut line = text_bundle(
+        status.label(),
+        &fonts,
+        STATUS_FONT_SIZE,
+        palette.palette.text,
+        0f32,
+    );
+    line.style.position_type = PositionType::Absolute;
+    line.style.position = Rect {
+        bottom: Val::Px(STATUS_MARGIN),
+        left: Val::Px(STATUS_MARGIN),
+        ..Default::default()
+    };
+    commands
+        .spawn_bundle(line)
+        .insert(TextColorRole::Text)
+        .insert(OnlineStatusMarker)
+        .insert(MenuScreenMarker);
+}
+
+fn update_status_line(
+    status: Res<OnlineStatus>,
+    mut line: Query<&mut Text, With<OnlineStatusMarker>>,
+) {
+    if status.is_changed() {
+        if let Ok(mut text) = line.single_mut() {
+            text.sections[0].value = status.label();
+        }
+    }
+}
+
+fn parse_verdict(body: &str) -> Response {
+    serde_json::from_str(body).map_err(|error| error.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use super::{parse_verdict, Response};
+    use std::sync::mpsc::Sender;
+
+    pub fn configured_url() -> Option<String> {
+        std::env::var("SWITCH_LEADERBOARD_URL").ok()
+    }
+
+    pub fn post(url: String, body: String, sender: Sender<Response>) {
+        std::thread::spawn(move || {
+            let response = ureq::post(&url)
+                .set("Content-Type", "application/json")
+                .send_string(&body)
+                .map_err(|error| error.to_string())
+                .and_then(|response| response.into_string().map_err(|error| error.to_string()))
+                .and_then(|body| parse_verdict(&body));
+            sender.send(response).unwrap_or(());
+        });
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use super::{parse_verdict, Response};
+    use std::sync::mpsc::Sender;
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{Request, RequestInit};
+
+    pub fn configured_url() -> Option<String> {
+        None
+    }
+
+    async fn fetch(url: String, body: String) -> Result<String, JsValue> {
+        let mut init = RequestInit::new();
+        init.method("POST");
+        init.body(Some(&JsValue::from_str(&body)));
+        let request = Request::new_with_str_and_init(&url, &init)?;
+        request.headers().set("Content-Type", "application/json")?;
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+        let response: web_sys::Response = JsFuture::from(window.fetch_with_request(&request))
+            .await?
+            .dyn_into()?;
+        let text = JsFuture::from(response.text()?).await?;
+        text.as_string()
+            .ok_or_else(|| JsValue::from_str("response is not text"))
+    }
+
+    pub fn post(url: String, body: String, sender: Sender<Response>) {
+        wasm_bindgen_futures::spawn_local(async move {
+            let response = fetch(url, body)
+                .await
+                .map_err(|error| format!("{:?}", error))
+                .and_then(|body| parse_verdict(&body));
+            sender.send(response).unwrap_or(());
+        });
+    }
+}
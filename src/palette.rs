@@ -0,0 +1,288 @@
+use crate::settings::Settings;
+use crate::world::{MaterialResource, Shape, ShapeTextures};
+use crate::GameState;
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+const GHOST_ALPHA: f32 = 0.35f32;
+
+pub const PALETTES: [&str; 6] = [
+    "default",
+    "high_contrast",
+    "red_green",
+    "tritan",
+    "monochrome",
+    "light",
+];
+
+fn hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    Color::hex(hex.trim_start_matches('#'))
+        .map_err(|_| D::Error::custom(format!("invalid color {}", hex)))
+}
+
+fn hex_colors<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Color>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|hex| {
+            Color::hex(hex.trim_start_matches('#'))
+                .map_err(|_| D::Error::custom(format!("invalid color {}", hex)))
+        })
+        .collect()
+}
+
+fn default_other_players() -> Vec<Color> {
+    Palette::default().other_players
+}
+
+#[derive(Clone, Deserialize, TypeUuid)]
+#[uuid = "8b1e6a52-5f0e-4c1d-9a43-2f6d7c0e91b4"]
+pub struct Palette {
+    pub name: String,
+    #[serde(deserialize_with = "hex_color")]
+    pub ui_background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub player: Color,
+    // Colors of the second player onwards in multiplayer modes.
+    #[serde(default = "default_other_players", deserialize_with = "hex_colors")]
+    pub other_players: Vec<Color>,
+    #[serde(deserialize_with = "hex_color")]
+    pub bouncing_enemy: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub homing_mine: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub mine_ring: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub wall: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub bullet: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub text: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub timer_text: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub button: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub button_focused: Color,
+    #[serde(default)]
+    pub bouncing_enemy_shape: Shape,
+    #[serde(default)]
+    pub homing_mine_shape: Shape,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            name: "Default".to_string(),
+            ui_background: Color::rgb(0.3, 0.3, 0.3),
+            background: Color::rgb(0.5, 0.5, 0.5),
+            player: Color::rgb(0.7, 0.7, 0.7),
+            other_players: vec![
+                Color::rgb(0.2, 0.4, 0.8),
+                Color::rgb(0.8, 0.67, 0.0),
+                Color::rgb(0.56, 0.27, 0.68),
+            ],
+            bouncing_enemy: Color::rgb(0.7, 0.0, 0.0),
+            homing_mine: Color::rgb(0.7, 0.35, 0.0),
+            mine_ring: Color::rgba(0.7, 0.0, 0.0, 0.15),
+            wall: Color::rgb(0.2, 0.2, 0.2),
+            bullet: Color::rgb(0.0, 0.7, 0.7),
+            text: Color::WHITE,
+            timer_text: Color::rgba(1.0, 1.0, 1.0, 0.2),
+            button: Color::rgb(0.2, 0.2, 0.2),
+            button_focused: Color::rgb(0.4, 0.4, 0.4),
+            bouncing_enemy_shape: Shape::Circle,
+            homing_mine_shape: Shape::Diamond,
+        }
+    }
+}
+
+impl Palette {
+    pub fn text_color(&self, role: TextColorRole) -> Color {
+        match role {
+            TextColorRole::Text => self.text,
+            TextColorRole::Timer => self.timer_text,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PaletteLoader;
+
+impl AssetLoader for PaletteLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let palette: Palette = serde_json::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(palette));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["palette"]
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum TextColorRole {
+    Text,
+    Timer,
+}
+
+pub struct PaletteHandles(Vec<Handle<Palette>>);
+
+#[derive(Default)]
+pub struct ActivePalette {
+    applied: Option<usize>,
+    pub palette: Palette,
+}
+
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<Palette>()
+            .init_asset_loader::<PaletteLoader>()
+            .init_resource::<ActivePalette>()
+            .add_startup_system(load_palettes.system())
+            .add_system(apply_palette.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu).with_system(cycle_palette.system()),
+            );
+    }
+}
+
+pub fn load_palettes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = PALETTES
+        .iter()
+        .map(|name| asset_server.load(format!("palettes/{}.palette", name).as_str()))
+        .collect();
+    commands.insert_resource(PaletteHandles(handles));
+}
+
+fn selected_palette(settings: &Settings) -> usize {
+    PALETTES
+        .iter()
+        .position(|name| *name == settings.palette)
+        .unwrap_or(0)
+}
+
+pub fn step_palette(settings: &mut Settings, step: i32) {
+    let count = PALETTES.len() as i32;
+    let next = (selected_palette(settings) as i32 + step).rem_euclid(count);
+    settings.palette = PALETTES[next as usize].to_string();
+}
+
+pub fn cycle_palette(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::C) {
+        step_palette(&mut settings, 1);
+    }
+}
+
+// Ghosts reuse the palette's colors, faded so they read as not being part of the run.
+pub fn ghost_color(color: Color) -> Color {
+    let mut color = color;
+    color.set_a(GHOST_ALPHA);
+    color
+}
+
+pub fn apply_palette(
+    mut events: EventReader<AssetEvent<Palette>>,
+    palettes: Res<Assets<Palette>>,
+    handles: Res<PaletteHandles>,
+    settings: Res<Settings>,
+    mut active: ResMut<ActivePalette>,
+    material_resource: Res<MaterialResource>,
+    shape_textures: Res<ShapeTextures>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut texts: Query<(&TextColorRole, &mut Text)>,
+) {
+    let selected = selected_palette(&settings);
+    let handle = &handles.0[selected];
+    let reloaded = events
+        .iter()
+        .filter(|event| match event {
+            AssetEvent::Created { handle: loaded } | AssetEvent::Modified { handle: loaded } => {
+                loaded == handle
+            }
+            AssetEvent::Removed { .. } => false,
+        })
+        .count()
+        > 0;
+    if active.applied == Some(selected) && !reloaded {
+        return;
+    }
+    let palette = match palettes.get(handle) {
+        Some(palette) => palette.clone(),
+        None => return,
+    };
+
+    let colors = [
+        (
+            &material_resource.ui_background_material,
+            palette.ui_background,
+        ),
+        (&material_resource.background_material, palette.background),
+        (&material_resource.player_material, palette.player),
+        (
+            &material_resource.bouncing_enemy_material,
+            palette.bouncing_enemy,
+        ),
+        (&material_resource.homing_mine_material, palette.homing_mine),
+        (&material_resource.mine_ring_material, palette.mine_ring),
+        (&material_resource.wall_material, palette.wall),
+        (&material_resource.bullet_material, palette.bullet),
+        (&material_resource.ghost_material, ghost_color(palette.player)),
+        (
+            &material_resource.ghost_enemy_material,
+            ghost_color(palette.bouncing_enemy),
+        ),
+        (&material_resource.button_material, palette.button),
+        (
+            &material_resource.button_focused_material,
+            palette.button_focused,
+        ),
+    ];
+    let other_players = material_resource
+        .other_player_materials
+        .iter()
+        .zip(palette.other_players.iter().copied());
+    for (handle, color) in colors.iter().copied().chain(other_players) {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color;
+        }
+    }
+    let shapes = [
+        (
+            &material_resource.bouncing_enemy_material,
+            palette.bouncing_enemy_shape,
+        ),
+        (
+            &material_resource.homing_mine_material,
+            palette.homing_mine_shape,
+        ),
+    ];
+    for (handle, shape) in shapes.iter() {
+        if let Some(material) = materials.get_mut(*handle) {
+            material.texture = Some(shape_textures.get(*shape));
+        }
+    }
+    for (role, mut text) in texts.iter_mut() {
+        let color = palette.text_color(*role);
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
+
+    active.applied = Some(selected);
+    active.palette = palette;
+}
@@ -0,0 +1,273 @@
+use crate::events::{DeathEvent, FireEvent, ThrustEvent};
+use crate::player::{PlayerMarker, PlayerSlot};
+use crate::settings::Settings;
+use crate::world::{CharType, Force, MaterialResource};
+use bevy::prelude::*;
+use rand::distributions::Uniform;
+use rand::{thread_rng, Rng};
+
+const PARTICLE_DEPTH: f32 = -0.005;
+const PARTICLE_DRAG: f32 = 4f32;
+
+const DEBRIS_COUNT: usize = 12;
+const DEBRIS_SPEED: f32 = 160f32;
+const DEBRIS_SIZE: f32 = 5f32;
+const DEBRIS_LIFE: f32 = 0.6f32;
+
+const FLASH_COUNT: usize = 5;
+const FLASH_SPEED: f32 = 140f32;
+const FLASH_SPREAD: f32 = 0.5f32;
+const FLASH_SIZE: f32 = 4f32;
+const FLASH_LIFE: f32 = 0.15f32;
+
+const THRUST_BURST_COUNT: usize = 8;
+const TRAIL_INTERVAL: f32 = 0.03f32;
+const TRAIL_SPEED: f32 = 50f32;
+const TRAIL_SPREAD: f32 = 0.3f32;
+const TRAIL_SIZE: f32 = 6f32;
+const TRAIL_LIFE: f32 = 0.4f32;
+// Decaying thrust only fades towards zero, so the trail stops once it is this weak.
+const TRAIL_MIN_FORCE: f32 = 10f32;
+
+const MAX_SHAKE_OFFSET: f32 = 12f32;
+const TRAUMA_DECAY: f32 = 1.5f32;
+const PLAYER_DEATH_TRAUMA: f32 = 0.8f32;
+const ENEMY_DEATH_TRAUMA: f32 = 0.25f32;
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(ScreenShake {
+            trauma: 0f32,
+            offset: Vec2::ZERO,
+        })
+            .add_system(spawn_debris.system())
+            .add_system(spawn_muzzle_flash.system())
+            .add_system(spawn_thrust_burst.system())
+            .add_system(thrust_trail.system())
+            .add_system(update_particles.system())
+            .add_system(apply_screen_shake.system());
+    }
+}
+
+pub struct Particle {
+    velocity: Vec2,
+    life: f32,
+    max_life: f32,
+    size: f32,
+}
+
+#[derive(Bundle)]
+pub struct ParticleBundle {
+    particle: Particle,
+    #[bundle]
+    sprite: SpriteBundle,
+}
+
+fn new_particle(
+    material: Handle<ColorMaterial>,
+    location: Vec2,
+    velocity: Vec2,
+    size: f32,
+    life: f32,
+) -> ParticleBundle {
+    ParticleBundle {
+        particle: Particle {
+            velocity,
+            life,
+            max_life: life,
+            size,
+        },
+        sprite: SpriteBundle {
+            sprite: Sprite::new(Vec2::new(size, size)),
+            transform: Transform::from_xyz(location.x, location.y, PARTICLE_DEPTH),
+            material,
+            ..Default::default()
+        },
+    }
+}
+
+fn spawn_cone(
+    commands: &mut Commands,
+    material: &Handle<ColorMaterial>,
+    location: Vec2,
+    direction: Vec2,
+    spread: f32,
+    speed: f32,
+    count: usize,
+    size: f32,
+    life: f32,
+) {
+    let base_angle = direction.y.atan2(direction.x);
+    let angles = Uniform::new_inclusive(base_angle - spread, base_angle + spread);
+    let speeds = Uniform::new_inclusive(speed * 0.5f32, speed);
+    let mut rng = thread_rng();
+    for _ in 0..count {
+        let (sin, cos) = rng.sample(angles).sin_cos();
+        let velocity = Vec2::new(cos, sin) * rng.sample(speeds);
+        commands.spawn_bundle(new_particle(
+            material.clone(),
+            location,
+            velocity,
+            size,
+            life,
+        ));
+    }
+}
+
+pub fn spawn_debris(
+    mut commands: Commands,
+    mut deaths: EventReader<DeathEvent>,
+    settings: Res<Settings>,
+    materials: Res<MaterialResource>,
+    mut shake: ResMut<ScreenShake>,
+) {
+    for death in deaths.iter() {
+        let (material, trauma) = match death.char_type {
+            CharType::Player => (&materials.player_material, PLAYER_DEATH_TRAUMA),
+            CharType::Enemy => (&materials.bouncing_enemy_material, ENEMY_DEATH_TRAUMA),
+            CharType::Bullet => (&materials.bullet_material, 0f32),
+        };
+        shake.add(trauma);
+        if settings.particles {
+            spawn_cone(
+                &mut commands,
+                material,
+                death.location,
+                Vec2::X,
+                std::f32::consts::PI,
+                DEBRIS_SPEED,
+                DEBRIS_COUNT,
+                DEBRIS_SIZE,
+                DEBRIS_LIFE,
+            );
+        }
+    }
+}
+
+pub fn spawn_muzzle_flash(
+    mut commands: Commands,
+    mut fires: EventReader<FireEvent>,
+    settings: Res<Settings>,
+    materials: Res<MaterialResource>,
+) {
+    for fire in fires.iter() {
+        if settings.particles {
+            spawn_cone(
+                &mut commands,
+                &materials.bullet_material,
+                fire.location,
+                fire.direction,
+                FLASH_SPREAD,
+                FLASH_SPEED,
+                FLASH_COUNT,
+                FLASH_SIZE,
+                FLASH_LIFE,
+            );
+        }
+    }
+}
+
+pub fn spawn_thrust_burst(
+    mut commands: Commands,
+    mut thrusts: EventReader<ThrustEvent>,
+    settings: Res<Settings>,
+    materials: Res<MaterialResource>,
+) {
+    for thrust in thrusts.iter() {
+        if settings.particles {
+            spawn_cone(
+                &mut commands,
+                &materials.player_material,
+                thrust.location,
+                -thrust.direction,
+                TRAIL_SPREAD * 2f32,
+                TRAIL_SPEED * 2f32,
+                THRUST_BURST_COUNT,
+                TRAIL_SIZE,
+                TRAIL_LIFE,
+            );
+        }
+    }
+}
+
+pub fn thrust_trail(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    materials: Res<MaterialResource>,
+    mut since_last: Local<f32>,
+    player: Query<(&PlayerSlot, &Transform, &Force), With<PlayerMarker>>,
+) {
+    *since_last += time.delta_seconds();
+    if *since_last < TRAIL_INTERVAL {
+        return;
+    }
+    *since_last = 0f32;
+    if !settings.particles {
+        return;
+    }
+    for (slot, transform, force) in player.iter() {
+        if force.0.length_squared() > TRAIL_MIN_FORCE * TRAIL_MIN_FORCE {
+            spawn_cone(
+                &mut commands,
+                &materials.player(slot.0),
+                transform.translation.truncate(),
+                -force.0,
+                TRAIL_SPREAD,
+                TRAIL_SPEED,
+                1,
+                TRAIL_SIZE,
+                TRAIL_LIFE,
+            );
+        }
+    }
+}
+
+pub fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let delta_seconds = time.delta_seconds();
+    let drag = (-PARTICLE_DRAG * delta_seconds).exp();
+    for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        particle.life -= delta_seconds;
+        if particle.life <= 0f32 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation += (particle.velocity * delta_seconds).extend(0f32);
+        particle.velocity *= drag;
+        let scaled = particle.size * particle.life / particle.max_life;
+        sprite.size = Vec2::new(scaled, scaled);
+    }
+}
+
+pub struct ScreenShake {
+    trauma: f32,
+    pub offset: Vec2,
+}
+
+impl ScreenShake {
+    pub fn add(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1f32);
+    }
+}
+
+pub fn apply_screen_shake(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut shake: ResMut<ScreenShake>,
+) {
+    shake.trauma = (shake.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0f32);
+    let mut offset = Vec2::ZERO;
+    if settings.screen_shake && shake.trauma > 0f32 {
+        let amount = shake.trauma * shake.trauma * MAX_SHAKE_OFFSET;
+        let distribution = Uniform::new_inclusive(-amount, amount);
+        let mut rng = thread_rng();
+        offset = Vec2::new(rng.sample(distribution), rng.sample(distribution));
+    }
+    shake.offset = offset;
+}
@@ -0,0 +1,79 @@
+use crate::sim::{SimClock, SimConfig};
+use crate::world::{Force, Velocity};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct Mass(pub f32);
+
+// Rate at which velocity decays, as `exp(-drag * t)`, so the decay does not depend on the tick
+// rate.
+pub struct Drag(pub f32);
+
+// One-shot change in momentum, consumed on the next integration step.
+pub struct Impulse(pub Vec2);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Integrator {
+    SemiImplicitEuler,
+    Verlet,
+}
+
+impl Default for Integrator {
+    fn default() -> Self {
+        Integrator::SemiImplicitEuler
+    }
+}
+
+#[derive(Bundle)]
+pub struct PhysicsBundle {
+    pub mass: Mass,
+    pub drag: Drag,
+    pub impulse: Impulse,
+    pub force: Force,
+    pub velocity: Velocity,
+}
+
+impl PhysicsBundle {
+    pub fn new(mass: f32, drag: f32) -> Self {
+        PhysicsBundle {
+            mass: Mass(mass),
+            drag: Drag(drag),
+            impulse: Impulse(Vec2::ZERO),
+            force: Force(Vec2::ZERO),
+            velocity: Velocity(Vec2::ZERO),
+        }
+    }
+}
+
+pub fn integrate(
+    clock: Res<SimClock>,
+    config: Res<SimConfig>,
+    mut bodies: Query<(
+        &mut Transform,
+        &mut Velocity,
+        &mut Impulse,
+        &Force,
+        &Mass,
+        &Drag,
+    )>,
+) {
+    let dt = clock.delta;
+    for (mut transform, mut velocity, mut impulse, force, mass, drag) in bodies.iter_mut() {
+        let inverse_mass = 1f32 / mass.0;
+        velocity.0 += impulse.0 * inverse_mass;
+        impulse.0 = Vec2::ZERO;
+        let acceleration = force.0 * inverse_mass;
+        match config.integrator {
+            Integrator::SemiImplicitEuler => {
+                velocity.0 += acceleration * dt;
+                velocity.0 *= (-drag.0 * dt).exp();
+                transform.translation += (velocity.0 * dt).extend(0f32);
+            }
+            Integrator::Verlet => {
+                let half_step = velocity.0 + 0.5f32 * (acceleration - drag.0 * velocity.0) * dt;
+                transform.translation += (half_step * dt).extend(0f32);
+                velocity.0 = half_step + 0.5f32 * (acceleration - drag.0 * half_step) * dt;
+            }
+        }
+    }
+}
@@ -1,104 +1,410 @@
-use crate::bullet::new_bullet;
+use crate::bullet::{new_bullet, BULLET_VELOCITY};
+use crate::collision::Collider;
+use crate::daily::Modifier;
+use crate::events::{FireEvent, ThrustEvent};
+use crate::menu::{ConnectedGamepads, GameMode};
+use crate::physics::{Impulse, PhysicsBundle};
+use crate::settings::Settings;
+use crate::sim::{
+    InputCommand, InputLog, InputQueue, NetInput, Playback, SimClock, SimConfig,
+};
 use crate::walls::WallDeathMarker;
 use crate::world::{
-    CharType, Counter, DefaultSize, Force, Location, MaterialResource, MouseLoc, ObjectMarker,
-    Target, Velocity,
+    CharType, Counter, Force, MaterialResource, MouseLoc, ObjectMarker, Target, Velocity,
 };
 use bevy::prelude::*;
-use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
 
 pub const PLAYER_SIZE: f32 = 20f32;
 
 const POWER: f32 = 175f32;
+const MASS: f32 = 1f32;
+const DRAG: f32 = 0.6f32;
+
+const THRUST_IMPULSE: f32 = 200f32;
+const THRUST_DECAY: f32 = 1.5f32;
+const SEEK_SPEED: f32 = 300f32;
+const SEEK_GAIN: f32 = 4f32;
+const SEEK_SLOW_RADIUS: f32 = 100f32;
+const SEEK_ARRIVE_RADIUS: f32 = 4f32;
 
 const TEAM: u8 = 0;
+const BULLET_TEAM: u8 = 2;
+// Each versus player and their bullets get their own teams, so bullets hit the other player and
+// cancel out the other player's bullets.
+const VERSUS_TEAMS: [(u8, u8); 2] = [(TEAM, BULLET_TEAM), (3, 4)];
+const VERSUS_SPAWN_OFFSET: f32 = 150f32;
+const COOP_SPAWNS: [[f32; 2]; 4] = [[-40f32, 0f32], [40f32, 0f32], [0f32, 40f32], [0f32, -40f32]];
 
 const COOL_DOWN: f32 = 0.0f32;
 
+const AIM_DISTANCE: f32 = 150f32;
+const STICK_DEAD_ZONE: f32 = 0.3f32;
+
 pub struct PlayerMarker;
 
-pub struct Switch(bool);
+// Which player this is, also the player index of the input commands that move it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PlayerSlot(pub u8);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ControlScheme {
+    Mouse,
+    Keyboard,
+    Gamepad,
+}
+
+impl Default for ControlScheme {
+    fn default() -> Self {
+        ControlScheme::Mouse
+    }
+}
+
+impl ControlScheme {
+    pub const ALL: [ControlScheme; 3] = [
+        ControlScheme::Mouse,
+        ControlScheme::Keyboard,
+        ControlScheme::Gamepad,
+    ];
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ThrustModel {
+    // The force of the last thrust stays on until the next one.
+    Constant,
+    Impulse,
+    Sustained,
+    TargetSeeking,
+}
+
+impl Default for ThrustModel {
+    fn default() -> Self {
+        ThrustModel::Constant
+    }
+}
+
+impl ThrustModel {
+    pub const ALL: [ThrustModel; 4] = [
+        ThrustModel::Constant,
+        ThrustModel::Impulse,
+        ThrustModel::Sustained,
+        ThrustModel::TargetSeeking,
+    ];
+
+    pub fn next(self) -> Self {
+        match self {
+            ThrustModel::Constant => ThrustModel::Impulse,
+            ThrustModel::Impulse => ThrustModel::Sustained,
+            ThrustModel::Sustained => ThrustModel::TargetSeeking,
+            ThrustModel::TargetSeeking => ThrustModel::Constant,
+        }
+    }
+}
+
+pub struct Switch(pub bool);
 
-pub struct CoolDown(f32);
+pub struct CoolDown(pub f32);
 
 #[derive(Bundle)]
 pub struct PlayerBundle {
     marker: PlayerMarker,
+    slot: PlayerSlot,
     wall_marker: WallDeathMarker,
     object_marker: ObjectMarker,
     type_marker: CharType,
     #[bundle]
     sprite: SpriteBundle,
-    size: DefaultSize,
-    location: Location,
+    collider: Collider,
 
-    force: Force,
+    #[bundle]
+    physics: PhysicsBundle,
     target: Target,
-    velocity: Velocity,
     switch: Switch,
     cool_down: CoolDown,
 }
 
-pub fn new_player(mut commands: Commands, resource: Res<MaterialResource>) {
+// The teams of a player and of the bullets they fire.
+pub fn teams(mode: GameMode, slot: u8) -> (u8, u8) {
+    match mode {
+        GameMode::Versus => VERSUS_TEAMS[slot as usize % VERSUS_TEAMS.len()],
+        _ => (TEAM, BULLET_TEAM),
+    }
+}
+
+fn spawn_location(mode: GameMode, slot: u8) -> Vec2 {
+    match mode {
+        GameMode::Versus if slot == 0 => Vec2::new(-VERSUS_SPAWN_OFFSET, 0f32),
+        GameMode::Versus => Vec2::new(VERSUS_SPAWN_OFFSET, 0f32),
+        GameMode::Coop => {
+            let spawn = COOP_SPAWNS[slot as usize % COOP_SPAWNS.len()];
+            Vec2::new(spawn[0], spawn[1])
+        }
+        _ => Vec2::default(),
+    }
+}
+
+pub fn new_player_bundle(
+    mode: GameMode,
+    slot: u8,
+    location: Vec2,
+    material: Handle<ColorMaterial>,
+) -> PlayerBundle {
     let sprite_bundle = SpriteBundle {
         sprite: Sprite::new(Vec2::new(PLAYER_SIZE, PLAYER_SIZE)),
-        material: resource.player_material.clone(),
+        material,
+        transform: Transform::from_xyz(location.x, location.y, 0f32),
         ..Default::default()
     };
-    let player_bundle = PlayerBundle {
+    PlayerBundle {
         marker: PlayerMarker,
+        slot: PlayerSlot(slot),
         wall_marker: WallDeathMarker,
-        object_marker: ObjectMarker(TEAM),
+        object_marker: ObjectMarker(teams(mode, slot).0),
         type_marker: CharType::Player,
         sprite: sprite_bundle,
-        size: DefaultSize {
-            width: PLAYER_SIZE,
-            height: PLAYER_SIZE,
-        },
-        location: Location(Vec2::default()),
-        target: Target(Vec2::default()),
-        force: Force(Vec2::default()),
-        velocity: Velocity(Vec2::default()),
+        collider: Collider::aabb(PLAYER_SIZE, PLAYER_SIZE),
+        target: Target(None),
+        physics: PhysicsBundle::new(MASS, DRAG),
         switch: Switch(true),
         cool_down: CoolDown(0f32),
+    }
+}
+
+pub fn new_player(mut commands: Commands, config: Res<SimConfig>, resource: Res<MaterialResource>) {
+    for slot in 0..config.players {
+        commands.spawn_bundle(new_player_bundle(
+            config.mode,
+            slot,
+            spawn_location(config.mode, slot),
+            resource.player(slot),
+        ));
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InputDevice {
+    Mouse,
+    Keyboard,
+    // A single connected gamepad, or all of them summed together.
+    Gamepad(Option<usize>),
+}
+
+impl From<ControlScheme> for InputDevice {
+    fn from(scheme: ControlScheme) -> Self {
+        match scheme {
+            ControlScheme::Mouse => InputDevice::Mouse,
+            ControlScheme::Keyboard => InputDevice::Keyboard,
+            ControlScheme::Gamepad => InputDevice::Gamepad(None),
+        }
+    }
+}
+
+// The device of each player. In versus the first player has the mouse and the second the
+// configured keyboard or gamepad, in co-op the mouse and keyboard go first and every further
+// player gets a gamepad of their own.
+pub fn player_devices(config: &SimConfig, settings: &Settings) -> Vec<InputDevice> {
+    match config.mode {
+        GameMode::Versus => {
+            let second = match settings.control_scheme {
+                ControlScheme::Mouse => InputDevice::Keyboard,
+                scheme => scheme.into(),
+            };
+            vec![InputDevice::Mouse, second]
+        }
+        GameMode::Coop => (0..config.players as usize)
+            .map(|slot| match slot {
+                0 => InputDevice::Mouse,
+                1 => InputDevice::Keyboard,
+                _ => InputDevice::Gamepad(Some(slot - 2)),
+            })
+            .collect(),
+        _ => vec![settings.control_scheme.into()],
+    }
+}
+
+fn held_direction(keys: &Input<KeyCode>) -> Vec2 {
+    let mut direction = Vec2::ZERO;
+    if keys.pressed(KeyCode::W) || keys.pressed(KeyCode::Up) {
+        direction.y += 1f32;
+    }
+    if keys.pressed(KeyCode::S) || keys.pressed(KeyCode::Down) {
+        direction.y -= 1f32;
+    }
+    if keys.pressed(KeyCode::A) || keys.pressed(KeyCode::Left) {
+        direction.x -= 1f32;
+    }
+    if keys.pressed(KeyCode::D) || keys.pressed(KeyCode::Right) {
+        direction.x += 1f32;
+    }
+    direction
+}
+
+// The connected gamepads a device reads from.
+fn device_gamepads(gamepads: &ConnectedGamepads, index: Option<usize>) -> Vec<Gamepad> {
+    match index {
+        Some(index) => gamepads.0.get(index).copied().into_iter().collect(),
+        None => gamepads.0.clone(),
+    }
+}
+
+fn stick_direction(gamepads: &[Gamepad], axes: &Axis<GamepadAxis>) -> Vec2 {
+    let direction = gamepads.iter().fold(Vec2::ZERO, |total, gamepad| {
+        let x = axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickX));
+        let y = axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickY));
+        total + Vec2::new(x.unwrap_or(0f32), y.unwrap_or(0f32))
+    });
+    if direction.length() > STICK_DEAD_ZONE {
+        direction
+    } else {
+        Vec2::ZERO
+    }
+}
+
+// Queues a switch toward the aim point for the next simulation tick, whichever device produced
+// it.
+pub fn read_controls(
+    settings: Res<Settings>,
+    config: Res<SimConfig>,
+    mouse_input: Res<Input<MouseButton>>,
+    mouse_loc: Res<MouseLoc>,
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<ConnectedGamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    clock: Res<SimClock>,
+    playback: Res<Playback>,
+    mut last_directions: Local<Vec<Vec2>>,
+    mut queue: ResMut<InputQueue>,
+    mut net: ResMut<NetInput>,
+    players: Query<(&PlayerSlot, &Transform), With<PlayerMarker>>,
+) {
+    if playback.0 {
+        return;
+    }
+    // With netplay only the local player is read, with whatever device the settings pick.
+    let devices: Vec<(u8, InputDevice)> = match net.local_slot {
+        Some(slot) => vec![(slot, settings.control_scheme.into())],
+        None => player_devices(&config, &settings)
+            .into_iter()
+            .enumerate()
+            .map(|(slot, device)| (slot as u8, device))
+            .collect(),
+    };
+    let NetInput {
+        local_slot,
+        outgoing,
+    } = &mut *net;
+    let mut push = |slot: u8, aim: Vec2| match local_slot {
+        Some(_) => outgoing.push(InputCommand::new(clock.tick, slot, aim)),
+        None => queue.push(clock.tick, slot, aim),
     };
-    commands.spawn_bundle(player_bundle);
+    let count = devices.iter().map(|(slot, _)| *slot as usize + 1).max();
+    last_directions.resize(count.unwrap_or(0).max(last_directions.len()), Vec2::ZERO);
+    for (slot, device) in devices {
+        let (switch, direction) = match device {
+            InputDevice::Mouse => {
+                if mouse_input.just_pressed(MouseButton::Left) {
+                    push(slot, mouse_loc.location);
+                }
+                continue;
+            }
+            InputDevice::Keyboard => (keys.just_pressed(KeyCode::Space), held_direction(&keys)),
+            InputDevice::Gamepad(index) => {
+                let pads = device_gamepads(&gamepads, index);
+                (
+                    gamepad_buttons.get_just_pressed().any(|button| {
+                        pads.contains(&button.0)
+                            && (button.1 == GamepadButtonType::South
+                                || button.1 == GamepadButtonType::RightTrigger2)
+                    }),
+                    stick_direction(&pads, &gamepad_axes),
+                )
+            }
+        };
+        let last_direction = &mut last_directions[slot as usize];
+        if direction != Vec2::ZERO {
+            *last_direction = direction.normalize();
+        } else if *last_direction == Vec2::ZERO {
+            *last_direction = Vec2::Y;
+        }
+        if switch {
+            if let Some((_, transform)) = players.iter().find(|(player, _)| player.0 == slot) {
+                push(
+                    slot,
+                    transform.translation.truncate() + *last_direction * AIM_DISTANCE,
+                );
+            }
+        }
+    }
 }
 
 pub fn mouse_click(
     mut commands: Commands,
-    mouse_input: Res<Input<MouseButton>>,
-    mouse_loc: Res<MouseLoc>,
+    clock: Res<SimClock>,
+    config: Res<SimConfig>,
+    mut queue: ResMut<InputQueue>,
+    mut log: ResMut<InputLog>,
     materials: Res<MaterialResource>,
+    mut fires: EventWriter<FireEvent>,
+    mut thrusts: EventWriter<ThrustEvent>,
     mut query: Query<(
-        &mut PlayerMarker,
+        &PlayerSlot,
         &ObjectMarker,
-        &mut Location,
+        &Transform,
         &mut Force,
+        &mut Impulse,
         &mut Target,
         &mut Switch,
         &mut CoolDown,
     )>,
 ) {
-    if mouse_input.just_pressed(MouseButton::Left) {
-        if let Ok((_, _, location, mut force, mut target, mut switch, mut cool_down)) =
-        query.single_mut()
+    while queue.0.front().map_or(false, |command| command.tick <= clock.tick) {
+        let command = queue.0.pop_front().unwrap();
+        let aim = command.aim();
+        log.0.push(InputCommand::new(clock.tick, command.player, aim));
+        let player = query.iter_mut().find(|(slot, ..)| slot.0 == command.player);
+        if let Some((slot, _, transform, mut force, mut impulse, mut target, mut switch, mut cool_down)) =
+            player
         {
+            let location = transform.translation.truncate();
             if cool_down.0 <= 0.0 {
                 cool_down.0 = COOL_DOWN;
                 if switch.0 {
-                    target.0 = mouse_loc.location;
-                    force.0 = Vec2::new(
-                        mouse_loc.location.x - location.0.x,
-                        mouse_loc.location.y - location.0.y,
-                    ).normalize() * POWER;
+                    let direction = (aim - location).normalize();
+                    target.0 = None;
+                    match config.thrust_model {
+                        ThrustModel::Impulse => {
+                            force.0 = Vec2::ZERO;
+                            impulse.0 += direction * THRUST_IMPULSE;
+                        }
+                        ThrustModel::Constant | ThrustModel::Sustained => {
+                            force.0 = direction * POWER
+                        }
+                        ThrustModel::TargetSeeking => target.0 = Some(aim),
+                    }
+                    thrusts.send(ThrustEvent {
+                        location,
+                        direction,
+                    });
                 } else {
-                    let source = Vec2::new(location.0.x, location.0.y);
+                    let source = location;
+                    let speed = if config.has_modifier(Modifier::DoubleBulletSpeed) {
+                        BULLET_VELOCITY * 2f32
+                    } else {
+                        BULLET_VELOCITY
+                    };
                     commands.spawn_bundle(new_bullet(
-                        mouse_loc.location,
+                        aim,
                         source,
+                        speed,
+                        teams(config.mode, slot.0).1,
                         materials.bullet_material.clone(),
                     ));
+                    let direction = (aim - source).normalize();
+                    fires.send(FireEvent {
+                        location: source + PLAYER_SIZE * direction,
+                        direction,
+                    });
                 }
                 switch.0 = !switch.0;
             }
@@ -106,8 +412,42 @@ pub fn mouse_click(
     }
 }
 
-pub fn update_cool_down(time: Res<Time>, mut cool_down: Query<&mut CoolDown>) {
+pub fn update_cool_down(clock: Res<SimClock>, mut cool_down: Query<&mut CoolDown>) {
     for mut cool_down in cool_down.iter_mut() {
-        cool_down.0 -= time.delta_seconds();
+        cool_down.0 -= clock.delta;
+    }
+}
+
+pub fn update_thrust(
+    clock: Res<SimClock>,
+    config: Res<SimConfig>,
+    mut query: Query<(&Transform, &mut Velocity, &mut Force, &mut Target), With<PlayerMarker>>,
+) {
+    let delta_seconds = clock.delta;
+    for (transform, mut velocity, mut force, mut target) in query.iter_mut() {
+        match config.thrust_model {
+            ThrustModel::Constant | ThrustModel::Impulse => {}
+            ThrustModel::Sustained => {
+                if !config.has_modifier(Modifier::NoThrustDecay) {
+                    force.0 *= (-THRUST_DECAY * delta_seconds).exp();
+                }
+            }
+            ThrustModel::TargetSeeking => {
+                if let Some(point) = target.0 {
+                    let offset = point - transform.translation.truncate();
+                    let distance = offset.length();
+                    if distance < SEEK_ARRIVE_RADIUS {
+                        target.0 = None;
+                        force.0 = Vec2::ZERO;
+                        velocity.0 = Vec2::ZERO;
+                    } else {
+                        let speed = SEEK_SPEED * (distance / SEEK_SLOW_RADIUS).min(1f32);
+                        let desired = offset / distance * speed;
+                        force.0 =
+                            ((desired - velocity.0) * SEEK_GAIN).clamp_length_max(POWER * 2f32);
+                    }
+                }
+            }
+        }
     }
 }
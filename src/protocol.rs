@@ -0,0 +1,124 @@
+use crate::daily::{daily_seed, Modifier};
+use crate::date::Date;
+use crate::menu::GameMode;
+use crate::sim::{simulate, Replay, DEFAULT_TICK_RATE};
+use serde::{Deserialize, Serialize};
+
+// A client POSTs a `Submission` as JSON to `SUBMIT_PATH` and gets a `Verdict` back. The server
+// plays the replay back with `simulate` and only accepts the run if it ends the way the client
+// claims. Runs recorded on a different platform may round differently and fail verification.
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const SUBMIT_PATH: &str = "/submit";
+pub const LEADERBOARD_PATH: &str = "/leaderboard";
+
+const MAX_RUN_TIME: f32 = 3600f32;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Submission {
+    pub version: u32,
+    pub name: String,
+    pub replay: Replay,
+    pub claimed_time: f32,
+    pub claimed_score: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Verdict {
+    pub accepted: bool,
+    pub time: f32,
+    pub score: u32,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl Verdict {
+    fn rejected(reason: String) -> Self {
+        Verdict {
+            accepted: false,
+            time: 0f32,
+            score: 0,
+            reason: Some(reason),
+        }
+    }
+}
+
+// Ranked runs are one player against the normal enemies, and only daily runs have a modifier.
+// Daily runs have to be on the seed and modifier of today or yesterday in UTC, so a run that ends
+// after midnight still counts.
+fn check_config(replay: &Replay, today: Date) -> Result<(), String> {
+    let config = &replay.config;
+    if !config.enemies || config.players != 1 || config.revive {
+        return Err("only single player runs with enemies are ranked".to_string());
+    }
+    match config.mode {
+        GameMode::Daily => {
+            let date = (today.to_days() - 1..=today.to_days())
+                .map(Date::from_days)
+                .find(|date| daily_seed(*date) == replay.seed)
+                .ok_or_else(|| "seed is not the seed of a recent daily".to_string())?;
+            if config.modifier != Some(Modifier::for_date(date)) {
+                return Err(format!(
+                    "modifier is not the modifier of the {} daily",
+                    date
+                ));
+            }
+        }
+        _ => {
+            if config.modifier.is_some() {
+                return Err(format!("{} runs have no modifier", config.mode.name()));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn verify(submission: &Submission) -> Verdict {
+    if submission.version != PROTOCOL_VERSION {
+        return Verdict::rejected(format!(
+            "protocol version {} is not supported",
+            submission.version
+        ));
+    }
+    if !submission.replay.config.mode.ranked() {
+        return Verdict::rejected(format!(
+            "{} runs are not ranked",
+            submission.replay.config.mode.name()
+        ));
+    }
+    if let Err(reason) = check_config(&submission.replay, Date::today()) {
+        return Verdict::rejected(reason);
+    }
+    // The cost of a check grows with the tick rate, so only the rate the game runs at is taken.
+    let tick_rate = submission.replay.config.tick_rate;
+    if tick_rate != DEFAULT_TICK_RATE {
+        return Verdict::rejected(format!("tick rate {} is not supported", tick_rate));
+    }
+    if !(0f32..=MAX_RUN_TIME).contains(&submission.claimed_time) {
+        return Verdict::rejected("claimed time is out of range".to_string());
+    }
+    let tolerance = 1f32 / tick_rate as f32;
+    // Run one tick past the claim so a run that outlives it is caught.
+    let max_ticks = ((submission.claimed_time + tolerance) * tick_rate as f32).ceil() as u64 + 1;
+    let result = simulate(&submission.replay, max_ticks);
+    let reason = if !result.finished {
+        Some(format!("player is still alive at {:.2}s", result.time))
+    } else if (result.time - submission.claimed_time).abs() > tolerance {
+        Some(format!(
+            "run ended at {:.2}s, {:.2}s was claimed",
+            result.time, submission.claimed_time
+        ))
+    } else if result.score != submission.claimed_score {
+        Some(format!(
+            "run scored {}, {} was claimed",
+            result.score, submission.claimed_score
+        ))
+    } else {
+        None
+    };
+    Verdict {
+        accepted: reason.is_none(),
+        time: result.time,
+        score: result.score,
+        reason,
+    }
+}
@@ -0,0 +1,18 @@
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+
+// Seed of the current or last run, everything that affects gameplay draws from `GameRng` so a
+// run can be reproduced from it.
+pub struct RunSeed(pub u64);
+
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+pub fn random_seed() -> u64 {
+    thread_rng().gen()
+}
@@ -0,0 +1,157 @@
+use crate::camera::CameraMode;
+use crate::menu::GameMode;
+use crate::physics::Integrator;
+use crate::player::{ControlScheme, ThrustModel};
+use crate::storage;
+use crate::world::Difficulty;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "settings";
+const VOLUME_STEP: f32 = 0.1f32;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub particles: bool,
+    pub screen_shake: bool,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub palette: String,
+    pub integrator: Integrator,
+    pub thrust_model: ThrustModel,
+    pub camera_mode: CameraMode,
+    pub game_mode: GameMode,
+    pub control_scheme: ControlScheme,
+    pub difficulty: Difficulty,
+    pub player_name: String,
+    pub ghost: bool,
+    pub ghost_enemies: bool,
+    pub versus_rounds: u32,
+    pub versus_enemies: bool,
+    pub coop_players: u8,
+    pub coop_revive: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            particles: true,
+            screen_shake: true,
+            fullscreen: false,
+            vsync: true,
+            master_volume: 0.8,
+            music_volume: 0.5,
+            sfx_volume: 0.8,
+            palette: "default".to_string(),
+            integrator: Integrator::default(),
+            thrust_model: ThrustModel::default(),
+            camera_mode: CameraMode::default(),
+            game_mode: GameMode::default(),
+            control_scheme: ControlScheme::default(),
+            difficulty: Difficulty::default(),
+            player_name: String::new(),
+            ghost: true,
+            ghost_enemies: false,
+            versus_rounds: 3,
+            versus_enemies: false,
+            coop_players: 2,
+            coop_revive: true,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        storage::load(SETTINGS_KEY).unwrap_or_default()
+    }
+
+    pub fn music_gain(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    pub fn sfx_gain(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+}
+
+// Settings replaced by the launch options for this launch only, as the launch value and the value
+// saved before, which is what gets saved while the setting keeps the launch value.
+#[derive(Default)]
+pub struct LaunchOverrides {
+    pub game_mode: Option<(GameMode, GameMode)>,
+    pub difficulty: Option<(Difficulty, Difficulty)>,
+    pub fullscreen: Option<(bool, bool)>,
+}
+
+// Once the setting is changed in the game it is saved like any other.
+fn restore<T: Copy + PartialEq>(value: &mut T, launch_override: &mut Option<(T, T)>) {
+    match *launch_override {
+        Some((launch, saved)) if *value == launch => *value = saved,
+        _ => *launch_override = None,
+    }
+}
+
+impl LaunchOverrides {
+    fn persisted(&mut self, settings: &Settings) -> Settings {
+        let mut persisted = settings.clone();
+        restore(&mut persisted.game_mode, &mut self.game_mode);
+        restore(&mut persisted.difficulty, &mut self.difficulty);
+        restore(&mut persisted.fullscreen, &mut self.fullscreen);
+        persisted
+    }
+}
+
+pub fn save_settings(settings: Res<Settings>, mut overrides: ResMut<LaunchOverrides>) {
+    if settings.is_changed() && !settings.is_added() {
+        storage::save(SETTINGS_KEY, &overrides.persisted(&settings));
+    }
+}
+
+pub fn toggle_effects(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::F1) {
+        settings.particles = !settings.particles;
+    }
+    if keys.just_pressed(KeyCode::F2) {
+        settings.screen_shake = !settings.screen_shake;
+    }
+    if keys.just_pressed(KeyCode::F9) {
+        settings.thrust_model = settings.thrust_model.next();
+    }
+    if keys.just_pressed(KeyCode::F10) {
+        settings.camera_mode = settings.camera_mode.next();
+    }
+}
+
+pub fn nudge_volume(volume: &mut f32, step: i32) {
+    *volume = (*volume + VOLUME_STEP * step as f32).max(0f32).min(1f32);
+}
+
+fn step_volume(volume: &mut f32, keys: &Input<KeyCode>, down: KeyCode, up: KeyCode) {
+    if keys.just_pressed(down) {
+        nudge_volume(volume, -1);
+    }
+    if keys.just_pressed(up) {
+        nudge_volume(volume, 1);
+    }
+}
+
+pub fn adjust_volume(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    let pressed = [
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+    ];
+    if !pressed.iter().any(|key| keys.just_pressed(*key)) {
+        return;
+    }
+    step_volume(&mut settings.master_volume, &keys, KeyCode::F3, KeyCode::F4);
+    step_volume(&mut settings.music_volume, &keys, KeyCode::F5, KeyCode::F6);
+    step_volume(&mut settings.sfx_volume, &keys, KeyCode::F7, KeyCode::F8);
+}
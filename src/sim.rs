@@ -0,0 +1,443 @@
+use crate::coop::{reset_revives, revive_players, LastPositions};
+use crate::daily::Modifier;
+use crate::enemies::{homing_mine_spin, move_bouncing_enemy, update_mines};
+use crate::events::{DeathEvent, FireEvent, SpawnEvent, ThrustEvent};
+use crate::menu::GameMode;
+use crate::physics::{integrate, Integrator};
+use crate::player::{
+    mouse_click, new_player, update_cool_down, update_thrust, PlayerMarker, ThrustModel,
+};
+use crate::rng::{random_seed, GameRng, RunSeed};
+use crate::settings::Settings;
+use crate::stats::{count_kills, reset_score, Score};
+use crate::ui::{reset_timer, timer, BestTime, Chrono, GameStartTimer};
+use crate::walls::handle_walls;
+use crate::world::{
+    clear_world, handle_bounce, handle_object_collision, init_spawn, setup_walls, spawn_system,
+    update_counters, Difficulty, MaterialResource,
+};
+use crate::GameState;
+use bevy::core::CorePlugin;
+use bevy::ecs::schedule::ShouldRun;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub const SIM_STAGE: &str = "sim";
+pub const START_RUN: &str = "start_run";
+pub const DEFAULT_TICK_RATE: u32 = 60;
+const MAX_FRAME_TIME: f32 = 0.25f32;
+
+// Everything besides the seed and the inputs that decides how a run plays out, fixed when the
+// run starts.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimConfig {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub thrust_model: ThrustModel,
+    pub integrator: Integrator,
+    pub tick_rate: u32,
+    #[serde(default)]
+    pub modifier: Option<Modifier>,
+    #[serde(default = "enemies_default")]
+    pub enemies: bool,
+    #[serde(default = "players_default")]
+    pub players: u8,
+    #[serde(default)]
+    pub revive: bool,
+}
+
+fn enemies_default() -> bool {
+    true
+}
+
+fn players_default() -> u8 {
+    1
+}
+
+impl SimConfig {
+    pub fn new(settings: &Settings, tick_rate: u32) -> Self {
+        SimConfig {
+            mode: settings.game_mode,
+            difficulty: settings.difficulty,
+            thrust_model: settings.thrust_model,
+            integrator: settings.integrator,
+            tick_rate,
+            modifier: None,
+            enemies: settings.game_mode != GameMode::Versus || settings.versus_enemies,
+            players: match settings.game_mode {
+                GameMode::Versus => 2,
+                GameMode::Coop => settings.coop_players,
+                _ => 1,
+            },
+            revive: settings.game_mode == GameMode::Coop && settings.coop_revive,
+        }
+    }
+
+    pub fn has_modifier(&self, modifier: Modifier) -> bool {
+        self.modifier == Some(modifier)
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            mode: GameMode::default(),
+            difficulty: Difficulty::default(),
+            thrust_model: ThrustModel::default(),
+            integrator: Integrator::default(),
+            tick_rate: DEFAULT_TICK_RATE,
+            modifier: None,
+            enemies: true,
+            players: 1,
+            revive: false,
+        }
+    }
+}
+
+pub struct SimClock {
+    pub tick: u64,
+    pub delta: f32,
+}
+
+impl SimClock {
+    pub fn new(tick_rate: u32) -> Self {
+        SimClock {
+            tick: 0,
+            delta: 1f32 / tick_rate as f32,
+        }
+    }
+}
+
+// The game steps the simulation with real frame time, a headless run steps it as fast as it can
+// until the player dies or `max_ticks` is reached.
+pub enum SimDriver {
+    RealTime { accumulator: f32 },
+    Headless { max_ticks: u64 },
+}
+
+impl Default for SimDriver {
+    fn default() -> Self {
+        SimDriver::RealTime { accumulator: 0f32 }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputCommand {
+    pub tick: u64,
+    #[serde(default)]
+    pub player: u8,
+    pub aim: [f32; 2],
+}
+
+impl InputCommand {
+    pub fn new(tick: u64, player: u8, aim: Vec2) -> Self {
+        InputCommand {
+            tick,
+            player,
+            aim: [aim.x, aim.y],
+        }
+    }
+
+    pub fn aim(&self) -> Vec2 {
+        Vec2::new(self.aim[0], self.aim[1])
+    }
+}
+
+// Switch presses waiting for their tick, filled by the controls or by a replay.
+#[derive(Default)]
+pub struct InputQueue(pub VecDeque<InputCommand>);
+
+impl InputQueue {
+    pub fn push(&mut self, tick: u64, player: u8, aim: Vec2) {
+        self.0.push_back(InputCommand::new(tick, player, aim));
+    }
+}
+
+// Switch presses of the current run in the tick they were applied.
+#[derive(Default)]
+pub struct InputLog(pub Vec<InputCommand>);
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub inputs: Vec<InputCommand>,
+}
+
+// Replay to play back instead of a fresh run the next time the game starts.
+#[derive(Default)]
+pub struct ReplayRequest(pub Option<Replay>);
+
+// Seed for every fresh run instead of a random one, set from the command line.
+#[derive(Default)]
+pub struct FixedSeed(pub Option<u64>);
+
+// How fast the simulation runs against the wall clock, only changed for testing.
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1f32)
+    }
+}
+
+// Set when the developer console changed anything during the current run, which then does not
+// go on the leaderboard.
+#[derive(Default)]
+pub struct Cheated(pub bool);
+
+// Whether the current run is playing back a replay rather than taking live input.
+#[derive(Default)]
+pub struct Playback(pub bool);
+
+// Set while the other players are driven from another machine. Switches of the local player go
+// out through `outgoing` instead of straight into the queue.
+#[derive(Default)]
+pub struct NetInput {
+    pub local_slot: Option<u8>,
+    pub outgoing: Vec<InputCommand>,
+}
+
+// The tick the simulation has to stop before, when something else decides how far it may run.
+#[derive(Default)]
+pub struct TickGate(pub Option<u64>);
+
+pub struct SimPlugin;
+
+impl Plugin for SimPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(SimClock::new(DEFAULT_TICK_RATE))
+            .init_resource::<SimConfig>()
+            .init_resource::<SimDriver>()
+            .init_resource::<InputQueue>()
+            .init_resource::<InputLog>()
+            .init_resource::<ReplayRequest>()
+            .init_resource::<Playback>()
+            .init_resource::<FixedSeed>()
+            .init_resource::<TimeScale>()
+            .init_resource::<Cheated>()
+            .init_resource::<LastPositions>()
+            .init_resource::<NetInput>()
+            .init_resource::<TickGate>()
+            .init_resource::<MaterialResource>()
+            .insert_resource(RunSeed(0))
+            .insert_resource(GameRng::new(0))
+            .insert_resource(Score(0))
+            .insert_resource(BestTime(0f32))
+            .insert_resource(GameStartTimer {
+                current_time: Chrono(0f32),
+            })
+            .add_event::<DeathEvent>()
+            .add_event::<FireEvent>()
+            .add_event::<SpawnEvent>()
+            .add_event::<ThrustEvent>()
+            .add_system_to_stage(CoreStage::PreUpdate, accumulate_sim_time.system())
+            // Single threaded so the systems always run in this order.
+            .add_stage_after(
+                CoreStage::Update,
+                SIM_STAGE,
+                SystemStage::single_threaded()
+                    .with_run_criteria(sim_should_run.system())
+                    .with_system(revive_players.system())
+                    .with_system(mouse_click.system())
+                    .with_system(update_thrust.system())
+                    .with_system(integrate.system())
+                    .with_system(move_bouncing_enemy.system())
+                    .with_system(update_mines.system())
+                    .with_system(homing_mine_spin.system())
+                    .with_system(handle_bounce.system())
+                    .with_system(handle_walls.system())
+                    .with_system(handle_object_collision.system())
+                    .with_system(update_counters.system())
+                    .with_system(spawn_system.system())
+                    .with_system(update_cool_down.system())
+                    .with_system(timer.system())
+                    .with_system(count_kills.system())
+                    .with_system(end_tick.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game)
+                    .with_system(start_run.system().label(START_RUN))
+                    .with_system(new_player.system().after(START_RUN))
+                    .with_system(setup_walls.system())
+                    .with_system(init_spawn.system())
+                    .with_system(reset_score.system())
+                    .with_system(reset_timer.system()),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Game).with_system(end_round.system()))
+            .add_system_set(
+                SystemSet::on_exit(GameState::Game)
+                    .with_system(clear_world.system())
+                    .with_system(reset_revives.system()),
+            );
+    }
+}
+
+pub fn start_run(
+    settings: Res<Settings>,
+    fixed_seed: Res<FixedSeed>,
+    mut request: ResMut<ReplayRequest>,
+    mut playback: ResMut<Playback>,
+    mut config: ResMut<SimConfig>,
+    mut clock: ResMut<SimClock>,
+    mut driver: ResMut<SimDriver>,
+    mut queue: ResMut<InputQueue>,
+    mut net: ResMut<NetInput>,
+    mut log: ResMut<InputLog>,
+    mut seed: ResMut<RunSeed>,
+    mut rng: ResMut<GameRng>,
+    mut cheated: ResMut<Cheated>,
+) {
+    queue.0.clear();
+    cheated.0 = false;
+    net.outgoing.clear();
+    log.0.clear();
+    playback.0 = request.0.is_some();
+    if let Some(replay) = request.0.take() {
+        *config = replay.config;
+        seed.0 = replay.seed;
+        queue.0.extend(replay.inputs);
+    } else {
+        *config = SimConfig::new(&settings, config.tick_rate);
+        seed.0 = fixed_seed.0.unwrap_or_else(random_seed);
+    }
+    *rng = GameRng::new(seed.0);
+    *clock = SimClock::new(config.tick_rate);
+    if let SimDriver::RealTime { accumulator } = &mut *driver {
+        *accumulator = 0f32;
+    }
+}
+
+pub fn accumulate_sim_time(
+    time: Res<Time>,
+    scale: Res<TimeScale>,
+    mut driver: ResMut<SimDriver>,
+) {
+    if let SimDriver::RealTime { accumulator } = &mut *driver {
+        *accumulator += time.delta_seconds().min(MAX_FRAME_TIME) * scale.0;
+    }
+}
+
+pub fn sim_should_run(
+    state: Res<State<GameState>>,
+    config: Res<SimConfig>,
+    clock: Res<SimClock>,
+    gate: Res<TickGate>,
+    mut driver: ResMut<SimDriver>,
+    players: Query<Entity, With<PlayerMarker>>,
+) -> ShouldRun {
+    if *state.current() != GameState::Game || config.mode.round_over(players.iter().count()) {
+        return ShouldRun::No;
+    }
+    if gate.0.map_or(false, |limit| clock.tick >= limit) {
+        // A stalled simulation does not bank time to catch up with in one burst.
+        if let SimDriver::RealTime { accumulator } = &mut *driver {
+            *accumulator = accumulator.min(clock.delta);
+        }
+        return ShouldRun::No;
+    }
+    match &mut *driver {
+        SimDriver::RealTime { accumulator } => {
+            if *accumulator >= clock.delta {
+                *accumulator -= clock.delta;
+                ShouldRun::YesAndCheckAgain
+            } else {
+                ShouldRun::No
+            }
+        }
+        SimDriver::Headless { max_ticks } => {
+            if clock.tick < *max_ticks {
+                ShouldRun::YesAndCheckAgain
+            } else {
+                ShouldRun::No
+            }
+        }
+    }
+}
+
+pub fn end_tick(mut clock: ResMut<SimClock>) {
+    clock.tick += 1;
+}
+
+// Players are despawned at the end of the tick they die in, so the round is ended from the next
+// frame once the mode says enough of them are gone.
+pub fn end_round(
+    config: Res<SimConfig>,
+    clock: Res<SimClock>,
+    mut state: ResMut<State<GameState>>,
+    players: Query<Entity, With<PlayerMarker>>,
+) {
+    if clock.tick > 0 && config.mode.round_over(players.iter().count()) {
+        state.set(GameState::GameOver).unwrap_or(());
+    }
+}
+
+pub struct RunResult {
+    pub ticks: u64,
+    pub time: f32,
+    pub score: u32,
+    pub finished: bool,
+}
+
+// Plays a replay back without a window or renderer.
+pub struct HeadlessSim {
+    app: App,
+}
+
+impl HeadlessSim {
+    pub fn new(replay: &Replay) -> Self {
+        let mut builder = App::build();
+        builder
+            .add_plugin(CorePlugin)
+            .insert_resource(Settings::default())
+            .insert_resource(SimDriver::Headless { max_ticks: 0 })
+            .insert_resource(ReplayRequest(Some(replay.clone())))
+            .add_plugin(SimPlugin)
+            .add_state(GameState::Game);
+        let mut app = builder.app;
+        // Enters the game without running a tick.
+        app.update();
+        HeadlessSim { app }
+    }
+
+    // Runs ticks until the clock reaches `tick` or the round is over.
+    pub fn advance_to(&mut self, tick: u64) {
+        *self.app.world.get_resource_mut::<SimDriver>().unwrap() =
+            SimDriver::Headless { max_ticks: tick };
+        self.app.update();
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    pub fn result(&mut self) -> RunResult {
+        let world = &mut self.app.world;
+        let alive = world
+            .query_filtered::<Entity, With<PlayerMarker>>()
+            .iter(world)
+            .count();
+        let finished = world
+            .get_resource::<SimConfig>()
+            .unwrap()
+            .mode
+            .round_over(alive);
+        RunResult {
+            ticks: world.get_resource::<SimClock>().unwrap().tick,
+            time: world
+                .get_resource::<GameStartTimer>()
+                .unwrap()
+                .current_time
+                .0,
+            score: world.get_resource::<Score>().unwrap().0,
+            finished,
+        }
+    }
+}
+
+pub fn simulate(replay: &Replay, max_ticks: u64) -> RunResult {
+    let mut sim = HeadlessSim::new(replay);
+    sim.advance_to(max_ticks);
+    sim.result()
+}
@@ -0,0 +1,268 @@
+use crate::events::{DeathEvent, FireEvent, SpawnEvent};
+use crate::menu::GameMode;
+use crate::player::PlayerSlot;
+use crate::sim::{SimClock, SimConfig, SIM_STAGE, START_RUN};
+use crate::stats::Score;
+use crate::ui::GameStartTimer;
+use crate::world::{CharType, ObjectMarker, Velocity};
+use crate::GameState;
+use bevy::prelude::*;
+use serde::Serialize;
+use std::io::{ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:7880";
+// A spectator that falls this far behind is disconnected rather than slowing the game down.
+const MAX_BACKLOG: usize = 1 << 20;
+
+#[derive(Serialize)]
+struct EntityState {
+    id: u32,
+    kind: &'static str,
+    team: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slot: Option<u8>,
+    position: [f32; 2],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    velocity: Option<[f32; 2]>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum EventState {
+    Spawn {
+        kind: &'static str,
+        position: [f32; 2],
+    },
+    Death {
+        kind: &'static str,
+        position: [f32; 2],
+    },
+    Fire {
+        position: [f32; 2],
+        direction: [f32; 2],
+    },
+}
+
+// Every line sent to spectators is one of these as JSON.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Line<'a> {
+    Start {
+        mode: GameMode,
+        tick_rate: u32,
+    },
+    Tick {
+        tick: u64,
+        time: f32,
+        score: u32,
+        entities: &'a [EntityState],
+        events: &'a [EventState],
+    },
+    End {
+        tick: u64,
+        time: f32,
+        score: u32,
+    },
+}
+
+fn kind(char_type: CharType) -> &'static str {
+    match char_type {
+        CharType::Player => "player",
+        CharType::Bullet => "bullet",
+        CharType::Enemy => "enemy",
+    }
+}
+
+struct Spectator {
+    stream: TcpStream,
+    backlog: Vec<u8>,
+}
+
+impl Spectator {
+    // Writes what the socket takes without blocking, false once the spectator is gone.
+    fn flush(&mut self) -> bool {
+        while !self.backlog.is_empty() {
+            match self.stream.write(&self.backlog) {
+                Ok(0) => return false,
+                Ok(written) => {
+                    self.backlog.drain(..written);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => return false,
+            }
+        }
+        self.backlog.len() < MAX_BACKLOG
+    }
+}
+
+pub struct SpectatorServer {
+    listener: TcpListener,
+    spectators: Vec<Spectator>,
+}
+
+impl SpectatorServer {
+    // Listens on `SWITCH_SPECTATE_ADDR`, or on the loopback when it is not set.
+    pub fn bind() -> std::io::Result<Self> {
+        let address = std::env::var("SWITCH_SPECTATE_ADDR")
+            .unwrap_or_else(|_| DEFAULT_ADDRESS.to_string())
+            .parse::<SocketAddr>()
+            .map_err(|error| std::io::Error::new(ErrorKind::InvalidInput, error))?;
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        info!("spectators can connect to {}", address);
+        Ok(SpectatorServer {
+            listener,
+            spectators: Vec::new(),
+        })
+    }
+
+    fn broadcast(&mut self, line: &Line) {
+        if self.spectators.is_empty() {
+            return;
+        }
+        let mut bytes = match serde_json::to_vec(line) {
+            Ok(bytes) => bytes,
+            Err(_) => return,
+        };
+        bytes.push(b'\n');
+        for spectator in self.spectators.iter_mut() {
+            spectator.backlog.extend_from_slice(&bytes);
+        }
+    }
+}
+
+#[derive(Default)]
+struct TickEvents(Vec<EventState>);
+
+pub struct SpectatePlugin;
+
+impl Plugin for SpectatePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let server = match SpectatorServer::bind() {
+            Ok(server) => server,
+            Err(error) => {
+                error!("could not start the spectator server: {}", error);
+                return;
+            }
+        };
+        app.insert_resource(server)
+            .init_resource::<TickEvents>()
+            .add_system(serve_spectators.system())
+            // Added after the simulation's own systems, so every tick is sent once it is done.
+            .add_system_to_stage(SIM_STAGE, collect_events.system())
+            .add_system_to_stage(SIM_STAGE, send_tick.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Game)
+                    .with_system(send_start.system().after(START_RUN)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Game).with_system(send_end.system()));
+    }
+}
+
+pub fn serve_spectators(mut server: ResMut<SpectatorServer>) {
+    while let Ok((stream, address)) = server.listener.accept() {
+        if stream.set_nonblocking(true).is_ok() {
+            info!("spectator connected from {}", address);
+            server.spectators.push(Spectator {
+                stream,
+                backlog: Vec::new(),
+            });
+        }
+    }
+    let spectators = std::mem::take(&mut server.spectators);
+    server.spectators = spectators
+        .into_iter()
+        .filter_map(|mut spectator| {
+            if spectator.flush() {
+                Some(spectator)
+            } else {
+                None
+            }
+        })
+        .collect();
+}
+
+fn collect_events(
+    mut events: ResMut<TickEvents>,
+    mut spawns: EventReader<SpawnEvent>,
+    mut deaths: EventReader<DeathEvent>,
+    mut fires: EventReader<FireEvent>,
+) {
+    events
+        .0
+        .extend(spawns.iter().map(|event| EventState::Spawn {
+            kind: kind(event.char_type),
+            position: event.location.into(),
+        }));
+    events
+        .0
+        .extend(deaths.iter().map(|event| EventState::Death {
+            kind: kind(event.char_type),
+            position: event.location.into(),
+        }));
+    events.0.extend(fires.iter().map(|event| EventState::Fire {
+        position: event.location.into(),
+        direction: event.direction.into(),
+    }));
+}
+
+fn send_tick(
+    clock: Res<SimClock>,
+    game_time: Res<GameStartTimer>,
+    score: Res<Score>,
+    mut events: ResMut<TickEvents>,
+    mut server: ResMut<SpectatorServer>,
+    objects: Query<(
+        Entity,
+        &Transform,
+        &CharType,
+        &ObjectMarker,
+        Option<&Velocity>,
+        Option<&PlayerSlot>,
+    )>,
+) {
+    let mut entities = objects
+        .iter()
+        .map(
+            |(entity, transform, char_type, team, velocity, slot)| EntityState {
+                id: entity.id(),
+                kind: kind(*char_type),
+                team: team.0,
+                slot: slot.map(|slot| slot.0),
+                position: transform.translation.truncate().into(),
+                velocity: velocity.map(|velocity| velocity.0.into()),
+            },
+        )
+        .collect::<Vec<_>>();
+    entities.sort_by_key(|entity| entity.id);
+    server.broadcast(&Line::Tick {
+        // The clock has already moved on to the next tick.
+        tick: clock.tick - 1,
+        time: game_time.current_time.0,
+        score: score.0,
+        entities: &entities,
+        events: &events.0,
+    });
+    events.0.clear();
+}
+
+fn send_start(config: Res<SimConfig>, mut server: ResMut<SpectatorServer>) {
+    server.broadcast(&Line::Start {
+        mode: config.mode,
+        tick_rate: config.tick_rate,
+    });
+}
+
+fn send_end(
+    clock: Res<SimClock>,
+    game_time: Res<GameStartTimer>,
+    score: Res<Score>,
+    mut server: ResMut<SpectatorServer>,
+) {
+    server.broadcast(&Line::End {
+        tick: clock.tick,
+        time: game_time.current_time.0,
+        score: score.0,
+    });
+}
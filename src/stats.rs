@@ -0,0 +1,50 @@
+use crate::events::DeathEvent;
+use crate::storage;
+use crate::ui::GameStartTimer;
+use crate::world::CharType;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const STATS_KEY: &str = "stats";
+const KILL_SCORE: u32 = 1;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub games_played: u32,
+    pub total_time: f32,
+    pub kills: u32,
+    pub best_score: u32,
+}
+
+impl Stats {
+    pub fn load() -> Self {
+        storage::load(STATS_KEY).unwrap_or_default()
+    }
+}
+
+pub struct Score(pub u32);
+
+pub fn reset_score(mut score: ResMut<Score>) {
+    score.0 = 0;
+}
+
+pub fn count_kills(mut deaths: EventReader<DeathEvent>, mut score: ResMut<Score>) {
+    for death in deaths.iter() {
+        if death.char_type == CharType::Enemy {
+            score.0 += KILL_SCORE;
+        }
+    }
+}
+
+pub fn record_game(
+    score: Res<Score>,
+    game_time: Res<GameStartTimer>,
+    mut stats: ResMut<Stats>,
+) {
+    stats.games_played += 1;
+    stats.kills += score.0 / KILL_SCORE;
+    stats.best_score = stats.best_score.max(score.0);
+    stats.total_time += game_time.current_time.0;
+    storage::save(STATS_KEY, &*stats);
+}
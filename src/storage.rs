@@ -0,0 +1,83 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const APP_NAME: &str = "switch";
+
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let contents = backend::read(key)?;
+    serde_json::from_str(&contents).ok()
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    if let Ok(contents) = serde_json::to_string(value) {
+        backend::write(key, &contents);
+    }
+}
+
+pub fn remove(key: &str) {
+    backend::remove(key);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use super::APP_NAME;
+    use std::path::PathBuf;
+
+    fn data_dir() -> PathBuf {
+        let base = std::env::var_os("APPDATA")
+            .or_else(|| std::env::var_os("XDG_DATA_HOME"))
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+            .unwrap_or_else(|| PathBuf::from("."));
+        base.join(APP_NAME)
+    }
+
+    pub fn read(key: &str) -> Option<String> {
+        std::fs::read_to_string(data_dir().join(format!("{}.json", key))).ok()
+    }
+
+    pub fn write(key: &str, contents: &str) {
+        let dir = data_dir();
+        if std::fs::create_dir_all(&dir).is_ok() {
+            std::fs::write(dir.join(format!("{}.json", key)), contents).unwrap_or(());
+        }
+    }
+
+    pub fn remove(key: &str) {
+        std::fs::remove_file(data_dir().join(format!("{}.json", key))).unwrap_or(());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use super::APP_NAME;
+    use web_sys::Storage;
+
+    fn local_storage() -> Option<Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn read(key: &str) -> Option<String> {
+        local_storage()?
+            .get_item(&format!("{}.{}", APP_NAME, key))
+            .ok()?
+    }
+
+    pub fn write(key: &str, contents: &str) {
+        if let Some(storage) = local_storage() {
+            storage
+                .set_item(&format!("{}.{}", APP_NAME, key), contents)
+                .unwrap_or(());
+        }
+    }
+
+    pub fn remove(key: &str) {
+        if let Some(storage) = local_storage() {
+            storage
+                .remove_item(&format!("{}.{}", APP_NAME, key))
+                .unwrap_or(());
+        }
+    }
+}
@@ -1,18 +1,43 @@
+use crate::menu::GameMode;
+use crate::palette::{ActivePalette, TextColorRole};
+use crate::player::{CoolDown, PlayerMarker, PlayerSlot};
+use crate::settings::Settings;
+use crate::sim::{NetInput, SimClock, SimConfig};
+use crate::stats::Score;
+use crate::versus::VersusMatch;
 use crate::world::{MaterialResource, ARENA_SIZE};
-use crate::GameState;
 use bevy::prelude::*;
-use bevy::text::Text2dSize;
 use bevy::window::WindowMode;
 
+const HUD_FONT_SIZE: f32 = 32f32;
+const HUD_MARGIN: f32 = 12f32;
+const LETTERBOX_SIZE: f32 = ARENA_SIZE * 10f32;
+
 pub fn set_windows(mut windows: ResMut<Windows>) {
     let window = windows.get_primary_mut().unwrap();
     window.set_title("switch".to_string());
 }
 
-pub struct DefaultFontSize(pub f32);
+pub fn apply_window_settings(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    let window = windows.get_primary_mut().unwrap();
+    let mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    if window.mode() != mode {
+        window.set_mode(mode);
+    }
+    if window.vsync() != settings.vsync {
+        window.set_vsync(settings.vsync);
+    }
+}
 
 pub struct Fonts {
-    font: Handle<Font>,
+    pub font: Handle<Font>,
 }
 
 pub fn init_fonts(mut commands: Commands, asset_server: Res<AssetServer>) {
@@ -20,146 +45,146 @@ pub fn init_fonts(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.insert_resource(Fonts { font })
 }
 
+pub fn text_bundle(
+    value: String,
+    fonts: &Fonts,
+    font_size: f32,
+    color: Color,
+    margin: f32,
+) -> TextBundle {
+    TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(margin)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: fonts.font.clone(),
+                font_size,
+                color,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    }
+}
+
 pub struct Chrono(pub f32);
 
 pub struct GameStartTimer {
     pub current_time: Chrono,
 }
 
-pub struct TimerUIMarker;
+pub struct BestTime(pub f32);
 
-#[derive(Bundle)]
-pub struct TimerUI {
-    pub marker: TimerUIMarker,
-    pub size: DefaultFontSize,
-    #[bundle]
-    pub text: Text2dBundle,
+pub struct HudMarker;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum HudText {
+    Time,
+    Best,
+    Score,
+    CoolDown,
 }
 
-pub fn init_timer(mut commands: Commands, fonts: Res<Fonts>) {
-    let text = Text::with_section(
-        "".to_string(),
-        TextStyle {
-            font_size: 0.0,
-            color: Color::Rgba {
-                red: 1.0,
-                green: 1.0,
-                blue: 1.0,
-                alpha: 0.2,
+pub fn init_hud(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    palette: Res<ActivePalette>,
+    materials: Res<MaterialResource>,
+) {
+    let root = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100f32), Val::Auto),
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(0f32),
+                left: Val::Px(0f32),
+                ..Default::default()
             },
-            font: fonts.font.clone(),
+            justify_content: JustifyContent::SpaceBetween,
+            ..Default::default()
         },
-        TextAlignment {
-            vertical: VerticalAlign::Center,
-            horizontal: HorizontalAlign::Center,
-        },
-    );
-    let text_bundle = Text2dBundle {
-        text,
+        material: materials.panel_material.clone(),
         ..Default::default()
     };
-    commands.spawn_bundle(TimerUI {
-        marker: TimerUIMarker,
-        size: DefaultFontSize(80.0),
-        text: text_bundle,
-    });
-    commands.insert_resource(GameStartTimer {
-        current_time: Chrono(0f32),
-    });
-}
-
-pub fn remove_timer(mut commands: Commands, timer_ui: Query<(Entity, &TimerUIMarker)>) {
-    if let Ok((entity, _)) = timer_ui.single() {
+    let fields = [
+        (HudText::Time, TextColorRole::Text),
+        (HudText::Best, TextColorRole::Timer),
+        (HudText::Score, TextColorRole::Text),
+        (HudText::CoolDown, TextColorRole::Timer),
+    ];
+    commands
+        .spawn_bundle(root)
+        .insert(HudMarker)
+        .with_children(|parent| {
+            for (field, role) in fields.iter() {
+                parent
+                    .spawn_bundle(text_bundle(
+                        String::new(),
+                        &fonts,
+                        HUD_FONT_SIZE,
+                        palette.palette.text_color(*role),
+                        HUD_MARGIN,
+                    ))
+                    .insert(*field)
+                    .insert(*role);
+            }
+        });
+}
+
+pub fn remove_hud(mut commands: Commands, hud: Query<Entity, With<HudMarker>>) {
+    for entity in hud.iter() {
         commands.entity(entity).despawn_recursive();
     }
-    commands.remove_resource::<GameStartTimer>();
+}
+
+pub fn reset_timer(mut game_time: ResMut<GameStartTimer>) {
+    game_time.current_time.0 = 0f32;
 }
 
 pub fn timer(
-    time: Res<Time>,
+    clock: Res<SimClock>,
     mut game_time: ResMut<GameStartTimer>,
-    mut timer_ui: Query<(&TimerUIMarker, &mut Text)>,
     mut best_time: ResMut<BestTime>,
 ) {
-    game_time.current_time.0 += time.delta_seconds();
+    game_time.current_time.0 += clock.delta;
     best_time.0 = game_time.current_time.0.max(best_time.0);
-    if let Ok((_, mut text)) = timer_ui.single_mut() {
-        text.sections[0].value = format!("{:.1} / {:.1}", game_time.current_time.0, best_time.0);
-    }
-}
-
-pub struct LeftClickToPlayMarker;
-
-#[derive(Bundle)]
-pub struct LeftClickToPlay {
-    marker: LeftClickToPlayMarker,
-    pub size: DefaultFontSize,
-    #[bundle]
-    text: Text2dBundle,
-}
-
-pub fn init_press_space_to_play(
-    mut commands: Commands,
-    fonts: Res<Fonts>,
-    best_time: Option<ResMut<BestTime>>,
-) {
-    let mut text = "Click To Play".to_string();
-    if let Some(best_time) = best_time {
-        text += &format!(" (Best: {:.1})", best_time.0);
-    }
-    let text = Text::with_section(
-        text,
-        TextStyle {
-            font: fonts.font.clone(),
-            font_size: 0.0,
-            color: Color::WHITE,
-        },
-        TextAlignment {
-            vertical: VerticalAlign::Center,
-            horizontal: HorizontalAlign::Center,
-        },
-    );
-    let text_bundle = Text2dBundle {
-        text,
-        ..Default::default()
-    };
-    commands.spawn_bundle(LeftClickToPlay {
-        marker: LeftClickToPlayMarker,
-        size: DefaultFontSize(40.0),
-        text: text_bundle,
-    });
 }
 
-pub fn update_left_click_to_play(
-    mut game_state: ResMut<State<GameState>>,
-    mouse: Res<Input<MouseButton>>,
+pub fn update_hud(
+    game_time: Res<GameStartTimer>,
+    best_time: Res<BestTime>,
+    score: Res<Score>,
+    config: Res<SimConfig>,
+    versus: Res<VersusMatch>,
+    net: Res<NetInput>,
+    players: Query<(&PlayerSlot, &CoolDown), With<PlayerMarker>>,
+    mut texts: Query<(&HudText, &mut Text)>,
 ) {
-    if let &GameState::Menu = game_state.current() {
-        if mouse.just_released(MouseButton::Left) {
-            game_state.set(GameState::Game).unwrap();
-        }
-    }
-}
-
-pub fn remove_left_click_to_play(
-    mut commands: Commands,
-    space_to_play: Query<(Entity, &LeftClickToPlayMarker)>,
-) {
-    if let Ok((entity, _)) = space_to_play.single() {
-        commands.entity(entity).despawn_recursive();
-    }
-}
-
-pub struct BestTime(pub f32);
-
-pub fn ui_scaling(windows: Res<Windows>, mut q: Query<(&DefaultFontSize, &mut Text)>) {
-    let window = windows.get_primary().unwrap();
-
-    let min = window.width().min(window.height());
-    for (font_size, mut text) in q.iter_mut() {
-        for section in text.sections.iter_mut() {
-            section.style.font_size = font_size.0 * min / ARENA_SIZE;
-        }
+    // The local player's, the first player's when everyone plays on this machine.
+    let slot = net.local_slot.unwrap_or(0);
+    let cool_down = players
+        .iter()
+        .find(|(player, _)| player.0 == slot)
+        .map_or(0f32, |(_, cool_down)| cool_down.0);
+    for (field, mut text) in texts.iter_mut() {
+        text.sections[0].value = match field {
+            HudText::Time => format!("Time {:.1}", game_time.current_time.0),
+            HudText::Best => format!("Best {:.1}", best_time.0),
+            HudText::Score => match config.mode {
+                GameMode::Versus => format!("Rounds {} - {}", versus.wins[0], versus.wins[1]),
+                _ => format!("Score {}", score.0),
+            },
+            HudText::CoolDown => {
+                if cool_down > 0f32 {
+                    format!("Wait {:.1}", cool_down)
+                } else {
+                    "Ready".to_string()
+                }
+            }
+        };
     }
 }
 
@@ -174,6 +199,7 @@ pub struct UiBackground {
 
 pub fn init_ui_background(mut commands: Commands, material: Res<MaterialResource>) {
     let sprite = SpriteBundle {
+        sprite: Sprite::new(Vec2::new(LETTERBOX_SIZE, LETTERBOX_SIZE)),
         material: material.ui_background_material.clone(),
         transform: Transform::from_xyz(0.0, 0.0, -0.02),
         ..Default::default()
@@ -183,14 +209,3 @@ pub fn init_ui_background(mut commands: Commands, material: Res<MaterialResource
         sprite,
     });
 }
-
-pub fn ui_background_scaling(
-    windows: Res<Windows>,
-    mut background: Query<(&UiBackgroundMarker, &mut Sprite)>,
-) {
-    let window = windows.get_primary().unwrap();
-
-    if let Ok((_, mut sprite)) = background.single_mut() {
-        sprite.size = Vec2::new(window.width(), window.height())
-    }
-}
@@ -0,0 +1,148 @@
+use crate::menu::{
+    menu_root, spawn_buttons, spawn_title, GameMode, MenuAction, MenuEvent, MenuScreenMarker,
+};
+use crate::palette::{ActivePalette, TextColorRole};
+use crate::player::{PlayerMarker, PlayerSlot};
+use crate::settings::Settings;
+use crate::sim::SimConfig;
+use crate::ui::{text_bundle, Fonts};
+use crate::world::MaterialResource;
+use crate::GameState;
+use bevy::prelude::*;
+
+const LINE_FONT_SIZE: f32 = 28f32;
+const LINE_SPACING: f32 = 4f32;
+
+// Score of the local versus match in progress, players are numbered from their slot.
+#[derive(Default)]
+pub struct VersusMatch {
+    pub best_of: u32,
+    pub wins: [u32; 2],
+    pub rounds: u32,
+    pub last_winner: Option<u8>,
+}
+
+impl VersusMatch {
+    pub fn new(best_of: u32) -> Self {
+        VersusMatch {
+            best_of,
+            ..Default::default()
+        }
+    }
+
+    pub fn needed(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+
+    pub fn champion(&self) -> Option<u8> {
+        self.wins
+            .iter()
+            .position(|wins| *wins >= self.needed())
+            .map(|slot| slot as u8)
+    }
+}
+
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<VersusMatch>()
+            .add_system(handle_versus_events.system())
+            .add_system_set(SystemSet::on_exit(GameState::Game).with_system(record_round.system()))
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(spawn_round_screen.system()),
+            );
+    }
+}
+
+pub fn handle_versus_events(
+    mut events: EventReader<MenuEvent>,
+    settings: Res<Settings>,
+    mut state: ResMut<State<GameState>>,
+    mut versus: ResMut<VersusMatch>,
+) {
+    for event in events.iter() {
+        match event.action {
+            MenuAction::Play => *versus = VersusMatch::new(settings.versus_rounds),
+            MenuAction::NextRound => state.set(GameState::Game).unwrap_or(()),
+            MenuAction::Rematch => {
+                *versus = VersusMatch::new(settings.versus_rounds);
+                state.set(GameState::Game).unwrap_or(());
+            }
+            _ => {}
+        }
+    }
+}
+
+// The round goes to the last player standing, or to nobody when they went down together.
+pub fn record_round(
+    config: Res<SimConfig>,
+    mut versus: ResMut<VersusMatch>,
+    players: Query<&PlayerSlot, With<PlayerMarker>>,
+) {
+    if config.mode != GameMode::Versus {
+        return;
+    }
+    let mut alive = players.iter();
+    let winner = match (alive.next(), alive.next()) {
+        (Some(slot), None) => Some(slot.0),
+        _ => None,
+    };
+    if let Some(slot) = winner {
+        versus.wins[slot as usize] += 1;
+    }
+    versus.rounds += 1;
+    versus.last_winner = winner;
+}
+
+pub fn spawn_round_screen(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    materials: Res<MaterialResource>,
+    palette: Res<ActivePalette>,
+    config: Res<SimConfig>,
+    versus: Res<VersusMatch>,
+) {
+    if config.mode != GameMode::Versus {
+        return;
+    }
+    let champion = versus.champion();
+    let title = match champion {
+        Some(slot) => format!("Player {} wins", slot + 1),
+        None => format!("Round {}", versus.rounds),
+    };
+    let lines = [
+        match versus.last_winner {
+            Some(slot) => format!("Player {} takes the round", slot + 1),
+            None => "Draw".to_string(),
+        },
+        format!(
+            "Player 1  {} - {}  Player 2",
+            versus.wins[0], versus.wins[1]
+        ),
+        format!("Best of {}", versus.best_of),
+    ];
+    let actions = if champion.is_some() {
+        [MenuAction::Rematch, MenuAction::Continue]
+    } else {
+        [MenuAction::NextRound, MenuAction::Continue]
+    };
+    commands
+        .spawn_bundle(menu_root(&materials))
+        .insert(MenuScreenMarker)
+        .with_children(|parent| {
+            spawn_title(parent, &title, &fonts, &palette);
+            for line in lines.iter() {
+                parent
+                    .spawn_bundle(text_bundle(
+                        line.clone(),
+                        &fonts,
+                        LINE_FONT_SIZE,
+                        palette.palette.text,
+                        LINE_SPACING,
+                    ))
+                    .insert(TextColorRole::Text);
+            }
+            spawn_buttons(parent, &actions, &fonts, &materials, &palette);
+        });
+}
@@ -1,7 +1,7 @@
-use crate::world::{CharType, DefaultSize, Location, MaterialResource};
-use crate::GameState;
+use crate::collision::{collide, Collider};
+use crate::events::{DeathCause, DeathEvent};
+use crate::world::{CharType, Invulnerable, MaterialResource};
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb::{collide, Collision};
 
 pub struct WallMarker;
 
@@ -10,17 +10,12 @@ pub struct WallBundle {
     marker: WallMarker,
     #[bundle]
     sprite: SpriteBundle,
-    size: DefaultSize,
-    location: Location,
+    collider: Collider,
 }
 
 pub fn new_wall(material: Handle<ColorMaterial>, translation: Vec2, size: Vec2) -> WallBundle {
-    let size = DefaultSize {
-        width: size.x,
-        height: size.y,
-    };
     let sprite_bundle = SpriteBundle {
-        sprite: Sprite::new(Vec2::new(size.width, size.height)),
+        sprite: Sprite::new(size),
         transform: Transform::from_translation(Vec3::new(translation.x, translation.y, 0f32)),
         material,
         ..Default::default()
@@ -28,8 +23,7 @@ pub fn new_wall(material: Handle<ColorMaterial>, translation: Vec2, size: Vec2)
     let wall = WallBundle {
         marker: WallMarker,
         sprite: sprite_bundle,
-        location: Location(Vec2::new(translation.x, translation.y)),
-        size,
+        collider: Collider::aabb(size.x, size.y),
     };
     wall
 }
@@ -38,23 +32,28 @@ pub struct WallDeathMarker;
 
 pub fn handle_walls(
     mut commands: Commands,
-    characters: Query<(&WallDeathMarker, Entity, &Location, &DefaultSize, &CharType)>,
-    walls: Query<(&WallMarker, &Location, &DefaultSize)>,
-    mut state: ResMut<State<GameState>>,
+    characters: Query<
+        (&WallDeathMarker, Entity, &Transform, &Collider, &CharType),
+        Without<Invulnerable>,
+    >,
+    walls: Query<(&WallMarker, &Transform, &Collider)>,
+    mut deaths: EventWriter<DeathEvent>,
 ) {
-    for (_, wall_location, wall_size) in walls.iter() {
-        for (_, entity, location, size, char_type) in characters.iter() {
-            if collide(
-                Vec3::new(wall_location.0.x, wall_location.0.y, 0f32),
-                Vec2::new(size.width, size.height),
-                Vec3::new(location.0.x, location.0.y, 0f32),
-                Vec2::new(wall_size.width, wall_size.height),
-            ).is_some()
-            {
-                if *char_type == CharType::Player {
-                    state.set(GameState::Menu).unwrap_or(());
-                }
+    let mut dead = Vec::new();
+    for (_, wall_transform, wall_collider) in walls.iter() {
+        let wall_location = wall_transform.translation.truncate();
+        for (_, entity, transform, collider, char_type) in characters.iter() {
+            let location = transform.translation.truncate();
+            if collide(wall_collider, wall_location, collider, location).is_some() {
                 commands.entity(entity).despawn_recursive();
+                if !dead.contains(&entity) {
+                    dead.push(entity);
+                    deaths.send(DeathEvent {
+                        location,
+                        char_type: *char_type,
+                        cause: DeathCause::Wall,
+                    });
+                }
             }
         }
     }
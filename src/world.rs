@@ -1,24 +1,31 @@
-use crate::enemies::{new_bouncing_enemy, new_homing_mine, BouncingEnemyBundle};
+use crate::collision::{collide, Collider};
+use crate::daily::Modifier;
+use crate::enemies::{
+    new_bouncing_enemy, new_homing_mine, new_mine_ring, BouncingEnemyBundle, HomingMineMarker,
+};
+use crate::events::{DeathCause, DeathEvent, SpawnEvent};
+use crate::palette::{ghost_color, Palette};
 use crate::player::PlayerMarker;
-use crate::walls::WallMarker;
-use crate::{walls, GameState};
+use crate::rng::GameRng;
+use crate::sim::{SimClock, SimConfig};
+use crate::walls::{self, WallMarker};
 use bevy::prelude::*;
-use bevy::render::camera::ScalingMode;
-use bevy::sprite::collide_aabb::{collide, Collision};
+use bevy::render::camera::{Camera, ScalingMode};
 use rand::distributions::Uniform;
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 pub const ARENA_SIZE: f32 = 600f32;
 const ARENA_MARGIN: f32 = 20f32;
 
-const DECAY: f32 = 0.01;
-
 pub struct MainCamera;
 
 pub fn setup(mut commands: Commands) {
-    commands
-        .spawn_bundle(OrthographicCameraBundle::new_2d())
-        .insert(MainCamera);
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.orthographic_projection.scaling_mode = ScalingMode::FixedVertical;
+    camera.orthographic_projection.scale = ARENA_SIZE * 0.5f32;
+    commands.spawn_bundle(camera).insert(MainCamera);
+    commands.spawn_bundle(UiCameraBundle::default());
 }
 
 pub struct MouseLoc {
@@ -33,58 +40,156 @@ pub fn setup_mouse(mut commands: Commands) {
 
 pub fn cursor_system(
     windows: Res<Windows>,
-    cam: Query<&Transform, With<MainCamera>>,
+    cam: Query<(&Camera, &Transform), With<MainCamera>>,
     mut mouse_resource: ResMut<MouseLoc>,
 ) {
     let window = windows.get_primary().unwrap();
     if let Some(pos) = window.cursor_position() {
         let size = Vec2::new(window.width() as f32, window.height() as f32);
-        let p = pos - size / 2.0;
-        let camera_transform = cam.single().unwrap();
-        let pos_wld = camera_transform.compute_matrix() * p.extend(0.0).extend(1.0);
+        let ndc = pos / size * 2.0 - Vec2::ONE;
+        let (camera, camera_transform) = cam.single().unwrap();
+        let pos_wld = camera_transform.compute_matrix()
+            * camera.projection_matrix.inverse()
+            * ndc.extend(0.0).extend(1.0);
         mouse_resource.location = Vec2::new(pos_wld.x, pos_wld.y);
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    Square,
+    Circle,
+    Diamond,
+    Triangle,
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Shape::Square
+    }
+}
+
+// Every shape, including the plain square, is a texture so switching shapes never changes the
+// material's shader defs.
+pub struct ShapeTextures {
+    square: Handle<Texture>,
+    circle: Handle<Texture>,
+    diamond: Handle<Texture>,
+    triangle: Handle<Texture>,
+    ring: Handle<Texture>,
+}
+
+impl ShapeTextures {
+    pub fn ring(&self) -> Handle<Texture> {
+        self.ring.clone()
+    }
+
+    pub fn get(&self, shape: Shape) -> Handle<Texture> {
+        match shape {
+            Shape::Square => self.square.clone(),
+            Shape::Circle => self.circle.clone(),
+            Shape::Diamond => self.diamond.clone(),
+            Shape::Triangle => self.triangle.clone(),
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct MaterialResource {
     pub ui_background_material: Handle<ColorMaterial>,
     pub background_material: Handle<ColorMaterial>,
     pub player_material: Handle<ColorMaterial>,
+    pub other_player_materials: Vec<Handle<ColorMaterial>>,
     pub bouncing_enemy_material: Handle<ColorMaterial>,
+    pub homing_mine_material: Handle<ColorMaterial>,
+    pub mine_ring_material: Handle<ColorMaterial>,
     pub wall_material: Handle<ColorMaterial>,
     pub bullet_material: Handle<ColorMaterial>,
+    pub ghost_material: Handle<ColorMaterial>,
+    pub ghost_enemy_material: Handle<ColorMaterial>,
+    pub button_material: Handle<ColorMaterial>,
+    pub button_focused_material: Handle<ColorMaterial>,
+    pub panel_material: Handle<ColorMaterial>,
 }
 
-pub fn init_material(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
-    let ui_background_material = materials.add(ColorMaterial::color(Color::rgb(0.3, 0.3, 0.3)));
-    let background_material = materials.add(ColorMaterial::color(Color::rgb(0.5, 0.5, 0.5)));
-    let player_material = materials.add(ColorMaterial::color(Color::rgb(0.7, 0.7, 0.7)));
-    let bouncing_enemy_material = materials.add(ColorMaterial::color(Color::rgb(0.7, 0.0, 0.0)));
-    let wall_material = materials.add(ColorMaterial::color(Color::rgb(0.2, 0.2, 0.2)));
-    let bullet_material = materials.add(ColorMaterial::color(Color::rgb(0.0, 0.7, 0.7)));
+impl MaterialResource {
+    pub fn player(&self, slot: u8) -> Handle<ColorMaterial> {
+        match slot {
+            0 => None,
+            _ => self.other_player_materials.get(slot as usize - 1),
+        }
+        .unwrap_or(&self.player_material)
+        .clone()
+    }
+}
+
+pub fn init_material(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let palette = Palette::default();
+    let textures = ShapeTextures {
+        square: asset_server.load("shapes/square.png"),
+        circle: asset_server.load("shapes/circle.png"),
+        diamond: asset_server.load("shapes/diamond.png"),
+        triangle: asset_server.load("shapes/triangle.png"),
+        ring: asset_server.load("shapes/ring.png"),
+    };
+    let ui_background_material = materials.add(ColorMaterial::color(palette.ui_background));
+    let background_material = materials.add(ColorMaterial::color(palette.background));
+    let player_material = materials.add(ColorMaterial::color(palette.player));
+    let other_player_materials = palette
+        .other_players
+        .iter()
+        .map(|color| materials.add(ColorMaterial::color(*color)))
+        .collect();
+    let bouncing_enemy_material = materials.add(ColorMaterial::modulated_texture(
+        textures.get(palette.bouncing_enemy_shape),
+        palette.bouncing_enemy,
+    ));
+    let homing_mine_material = materials.add(ColorMaterial::modulated_texture(
+        textures.get(palette.homing_mine_shape),
+        palette.homing_mine,
+    ));
+    let mine_ring_material = materials.add(ColorMaterial::modulated_texture(
+        textures.ring.clone(),
+        palette.mine_ring,
+    ));
+    let wall_material = materials.add(ColorMaterial::color(palette.wall));
+    let bullet_material = materials.add(ColorMaterial::color(palette.bullet));
+    let ghost_material = materials.add(ColorMaterial::color(ghost_color(palette.player)));
+    let ghost_enemy_material =
+        materials.add(ColorMaterial::color(ghost_color(palette.bouncing_enemy)));
+    let button_material = materials.add(ColorMaterial::color(palette.button));
+    let button_focused_material = materials.add(ColorMaterial::color(palette.button_focused));
+    let panel_material = materials.add(ColorMaterial::color(Color::NONE));
+    commands.insert_resource(textures);
     commands.insert_resource(MaterialResource {
         ui_background_material,
         background_material,
         player_material,
+        other_player_materials,
         bouncing_enemy_material,
+        homing_mine_material,
+        mine_ring_material,
         wall_material,
         bullet_material,
+        ghost_material,
+        ghost_enemy_material,
+        button_material,
+        button_focused_material,
+        panel_material,
     })
 }
 
 pub struct Force(pub Vec2);
 
-pub struct Target(pub Vec2);
+pub struct Target(pub Option<Vec2>);
 
 pub struct Velocity(pub Vec2);
 
-pub struct DefaultSize {
-    pub width: f32,
-    pub height: f32,
-}
-
-pub struct Location(pub Vec2);
-
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum CharType {
     Player,
@@ -92,26 +197,6 @@ pub enum CharType {
     Enemy,
 }
 
-pub fn size_scaling(windows: Res<Windows>, mut q: Query<(&DefaultSize, &mut Sprite)>) {
-    let window = windows.get_primary().unwrap();
-
-    let min = window.width().min(window.height());
-    for (sprite_size, mut sprite) in q.iter_mut() {
-        sprite.size.x = sprite_size.width / ARENA_SIZE as f32 * min;
-        sprite.size.y = sprite_size.height / ARENA_SIZE as f32 * min;
-    }
-}
-
-pub fn position_translation(windows: Res<Windows>, mut q: Query<(&Location, &mut Transform)>) {
-    let window = windows.get_primary().unwrap();
-
-    let ratio = window.width().min(window.height()) / ARENA_SIZE;
-    for (loc, mut transform) in q.iter_mut() {
-        transform.translation.x = loc.0.x * ratio;
-        transform.translation.y = loc.0.y * ratio;
-    }
-}
-
 pub fn setup_walls(mut commands: Commands, materials: Res<MaterialResource>) {
     let left_wall = walls::new_wall(
         materials.wall_material.clone(),
@@ -145,27 +230,47 @@ pub fn setup_walls(mut commands: Commands, materials: Res<MaterialResource>) {
 
 pub struct ObjectMarker(pub u8);
 
+// Survives collisions and walls, the other object still dies. Only given out for testing.
+pub struct Invulnerable;
+
 pub fn handle_object_collision(
     mut commands: Commands,
-    characters: Query<(&ObjectMarker, Entity, &Location, &DefaultSize, &CharType)>,
-    mut state: ResMut<State<GameState>>,
+    characters: Query<(
+        &ObjectMarker,
+        Entity,
+        &Transform,
+        &Collider,
+        &CharType,
+        Option<&HomingMineMarker>,
+        Option<&Invulnerable>,
+    )>,
+    mut deaths: EventWriter<DeathEvent>,
 ) {
-    for (marker_0, entity_0, location_0, size_0, type_0) in characters.iter() {
-        for (marker_1, entity_1, location_1, size_1, type_1) in characters.iter() {
+    let mut dead = Vec::new();
+    for (marker_0, entity_0, transform_0, collider_0, type_0, mine_0, safe_0) in characters.iter() {
+        let location_0 = transform_0.translation.truncate();
+        for (marker_1, entity_1, transform_1, collider_1, type_1, mine_1, safe_1) in
+            characters.iter()
+        {
+            let location_1 = transform_1.translation.truncate();
             if entity_0 != entity_1 {
                 if marker_0.0 != marker_1.0 {
-                    if collide(
-                        Vec3::new(location_0.0.x, location_0.0.y, 0f32),
-                        Vec2::new(size_0.width, size_0.height),
-                        Vec3::new(location_1.0.x, location_1.0.y, 0f32),
-                        Vec2::new(size_1.width, size_1.height),
-                    ).is_some()
-                    {
-                        commands.entity(entity_0).despawn_recursive();
-                        commands.entity(entity_1).despawn_recursive();
-                        if *type_0 == CharType::Player || *type_1 == CharType::Player {
-                            state.set(GameState::Menu).unwrap_or(());
-                            return;
+                    if collide(collider_0, location_0, collider_1, location_1).is_some() {
+                        let cause_0 = DeathCause::of(*type_1, mine_1.is_some());
+                        let cause_1 = DeathCause::of(*type_0, mine_0.is_some());
+                        for (entity, location, char_type, cause, safe) in [
+                            (entity_0, location_0, type_0, cause_0, safe_0),
+                            (entity_1, location_1, type_1, cause_1, safe_1),
+                        ] {
+                            if safe.is_none() && !dead.contains(&entity) {
+                                commands.entity(entity).despawn_recursive();
+                                dead.push(entity);
+                                deaths.send(DeathEvent {
+                                    location,
+                                    char_type: *char_type,
+                                    cause,
+                                });
+                            }
                         }
                     }
                 }
@@ -177,38 +282,21 @@ pub fn handle_object_collision(
 pub struct BounceMarker;
 
 pub fn handle_bounce(
-    mut bouncing_enemy: Query<(&BounceMarker, &DefaultSize, &Location, &mut Velocity)>,
-    walls: Query<(&WallMarker, &Location, &DefaultSize)>,
+    mut bouncing_enemy: Query<
+        (&Collider, &mut Transform, &mut Velocity),
+        (With<BounceMarker>, Without<WallMarker>),
+    >,
+    walls: Query<(&Transform, &Collider), With<WallMarker>>,
 ) {
-    for (_, size, location, mut velocity) in bouncing_enemy.iter_mut() {
-        for (_, wall_location, wall_size) in walls.iter() {
-            if let Some(collision) = collide(
-                Vec3::new(location.0.x, location.0.y, 0f32),
-                Vec2::new(size.width * 1.1, size.height * 1.1),
-                Vec3::new(wall_location.0.x, wall_location.0.y, 0f32),
-                Vec2::new(wall_size.width, wall_size.height),
-            ) {
-                match collision {
-                    Collision::Left => {
-                        if velocity.0.x > 0f32 {
-                            velocity.0.x *= -1f32;
-                        }
-                    }
-                    Collision::Right => {
-                        if velocity.0.x < 0f32 {
-                            velocity.0.x *= -1f32;
-                        }
-                    }
-                    Collision::Top => {
-                        if velocity.0.y < 0f32 {
-                            velocity.0.y *= -1f32;
-                        }
-                    }
-                    Collision::Bottom => {
-                        if velocity.0.y > 0f32 {
-                            velocity.0.y *= -1f32;
-                        }
-                    }
+    for (collider, mut transform, mut velocity) in bouncing_enemy.iter_mut() {
+        for (wall_transform, wall_collider) in walls.iter() {
+            let location = transform.translation.truncate();
+            let wall_location = wall_transform.translation.truncate();
+            if let Some(contact) = collide(collider, location, wall_collider, wall_location) {
+                transform.translation -= (contact.normal * contact.depth).extend(0f32);
+                let approach = velocity.0.dot(contact.normal);
+                if approach > 0f32 {
+                    velocity.0 -= 2f32 * approach * contact.normal;
                 }
             }
         }
@@ -219,10 +307,10 @@ pub struct Counter(pub f32);
 
 pub fn update_counters(
     mut commands: Commands,
-    time: Res<Time>,
+    clock: Res<SimClock>,
     mut query: Query<(Entity, &mut Counter)>,
 ) {
-    let delta_seconds = time.delta_seconds();
+    let delta_seconds = clock.delta;
     for (entity, mut counter) in query.iter_mut() {
         counter.0 -= delta_seconds;
         if counter.0 < 0f32 {
@@ -236,75 +324,182 @@ const DIFFICULTY_BASE: f32 = 0.5f32;
 const DIFFICULTY_MULTIPLIER: f32 = 0.1f32;
 
 const PLAYER_DISTANCE: f32 = 150f32;
+// With several players the exclusion circles can cover the whole arena.
+const SPAWN_ATTEMPTS: usize = 32;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Normal
+    }
+}
+
+pub struct DifficultyTuning {
+    pub spawn_base: f32,
+    pub spawn_multiplier: f32,
+    pub player_distance: f32,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn tuning(self) -> DifficultyTuning {
+        let (base, multiplier, distance) = match self {
+            Difficulty::Easy => (1.5f32, 1.5f32, 1.3f32),
+            Difficulty::Normal => (1f32, 1f32, 1f32),
+            Difficulty::Hard => (0.6f32, 0.7f32, 0.8f32),
+        };
+        DifficultyTuning {
+            spawn_base: DIFFICULTY_BASE * base,
+            spawn_multiplier: DIFFICULTY_MULTIPLIER * multiplier,
+            player_distance: PLAYER_DISTANCE * distance,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EnemyKind {
+    Bouncing,
+    Mine,
+}
 
 pub struct SpawnSystem {
     time_since_start: f32,
     time_since_last_spawn: f32,
+    pub paused: bool,
+    // Spawns asked for outside the simulation, made at the start of the next tick.
+    requests: Vec<(EnemyKind, Vec2)>,
+}
+
+impl SpawnSystem {
+    // Counts down from `MAX_DIFFICULTY` as the run goes on, the lower the more often enemies spawn.
+    pub fn difficulty(&self) -> f32 {
+        MAX_DIFFICULTY - self.time_since_start.sqrt().min(MAX_DIFFICULTY)
+    }
+
+    pub fn interval(&self, tuning: &DifficultyTuning) -> f32 {
+        tuning.spawn_base + self.difficulty() * tuning.spawn_multiplier
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time_since_start
+    }
+
+    // Jumps the difficulty to where it is this far into a run.
+    pub fn set_time(&mut self, time: f32) {
+        self.time_since_start = time.max(0f32);
+    }
+
+    pub fn request(&mut self, kind: EnemyKind, position: Vec2) {
+        self.requests.push((kind, position));
+    }
 }
 
 pub fn init_spawn(mut commands: Commands) {
     commands.insert_resource(SpawnSystem {
         time_since_start: 0f32,
         time_since_last_spawn: 0f32,
+        paused: false,
+        requests: Vec::new(),
     })
 }
 
+fn spawn_enemy<R: Rng>(
+    commands: &mut Commands,
+    material: &MaterialResource,
+    kind: EnemyKind,
+    position: Vec2,
+    rng: &mut R,
+    spawns: &mut EventWriter<SpawnEvent>,
+) {
+    match kind {
+        EnemyKind::Bouncing => {
+            commands.spawn_bundle(new_bouncing_enemy(
+                material.bouncing_enemy_material.clone(),
+                position,
+                rng,
+            ));
+        }
+        EnemyKind::Mine => spawn_homing_mine(commands, material, position),
+    }
+    spawns.send(SpawnEvent {
+        location: position,
+        char_type: CharType::Enemy,
+    });
+}
+
 pub fn spawn_system(
     mut commands: Commands,
-    time: Res<Time>,
+    clock: Res<SimClock>,
     mut spawn_system: ResMut<SpawnSystem>,
-    player: Query<(&PlayerMarker, &Location)>,
+    players: Query<&Transform, With<PlayerMarker>>,
     material: Res<MaterialResource>,
+    config: Res<SimConfig>,
+    mut rng: ResMut<GameRng>,
+    mut spawns: EventWriter<SpawnEvent>,
 ) {
-    let delta_seconds = time.delta_seconds();
+    let rng = &mut rng.0;
+    for (kind, position) in std::mem::take(&mut spawn_system.requests) {
+        spawn_enemy(&mut commands, &material, kind, position, rng, &mut spawns);
+    }
+    if !config.enemies || spawn_system.paused {
+        return;
+    }
+    let tuning = config.difficulty.tuning();
+    let delta_seconds = clock.delta;
     spawn_system.time_since_start += delta_seconds;
     spawn_system.time_since_last_spawn += delta_seconds;
-    let difficulty = MAX_DIFFICULTY - spawn_system.time_since_start.sqrt().min(MAX_DIFFICULTY);
-    if let Ok((_, location)) = player.single() {
-        if spawn_system.time_since_last_spawn > DIFFICULTY_BASE + difficulty * DIFFICULTY_MULTIPLIER
-        {
-            spawn_system.time_since_last_spawn = 0f32;
+    let players: Vec<Vec2> = players
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    if !players.is_empty() {
+        if spawn_system.time_since_last_spawn > spawn_system.interval(&tuning) {
             let distribution = Uniform::new(
                 -ARENA_SIZE * 0.5f32 + ARENA_MARGIN * 2f32,
                 ARENA_SIZE * 0.5f32 - ARENA_MARGIN * 2f32,
             );
-            let mut position;
-            loop {
-                let x = rand::thread_rng().sample(distribution);
-                let y = rand::thread_rng().sample(distribution);
-                position = Vec2::new(x, y);
-                if position.distance_squared(location.0) > PLAYER_DISTANCE * PLAYER_DISTANCE {
-                    break;
-                }
-            }
-            match rand::thread_rng().gen_range(0..2) {
-                0 => {
-                    commands.spawn_bundle(new_bouncing_enemy(
-                        material.bouncing_enemy_material.clone(),
-                        position,
-                    ));
-                }
-                1 => {
-                    commands.spawn_bundle(new_homing_mine(
-                        material.bouncing_enemy_material.clone(),
-                        position,
-                    ));
-                }
-                _ => {}
-            }
+            let position = (0..SPAWN_ATTEMPTS)
+                .map(|_| Vec2::new(rng.sample(distribution), rng.sample(distribution)))
+                .find(|position| {
+                    players.iter().all(|player| {
+                        position.distance_squared(*player)
+                            > tuning.player_distance * tuning.player_distance
+                    })
+                });
+            // With the players spread over the arena there may be nowhere far enough from all of
+            // them, then the spawn waits for a later tick.
+            let position = match position {
+                Some(position) => position,
+                None => return,
+            };
+            spawn_system.time_since_last_spawn = 0f32;
+            let kind = if config.has_modifier(Modifier::MinesOnly) || rng.gen_range(0..2) == 1 {
+                EnemyKind::Mine
+            } else {
+                EnemyKind::Bouncing
+            };
+            spawn_enemy(&mut commands, &material, kind, position, rng, &mut spawns);
         }
     }
 }
 
-pub fn slide_move(time: Res<Time>, mut query: Query<(&mut Location, &Force, &mut Velocity)>) {
-    let delta_seconds = time.delta_seconds();
-    for (mut location, force, mut velocity) in query.iter_mut() {
-        velocity.0 += force.0 * delta_seconds;
-        velocity.0 *= 1f32 - DECAY;
-        let move_amt = velocity.0 * delta_seconds;
-        location.0.x += move_amt.x;
-        location.0.y += move_amt.y;
-    }
+// Mines carry the ring that shows their range.
+pub fn spawn_homing_mine(commands: &mut Commands, material: &MaterialResource, position: Vec2) {
+    commands
+        .spawn_bundle(new_homing_mine(
+            material.homing_mine_material.clone(),
+            position,
+        ))
+        .with_children(|parent| {
+            parent.spawn_bundle(new_mine_ring(material.mine_ring_material.clone()));
+        });
 }
 
 pub fn clear_world(
@@ -325,26 +520,19 @@ pub struct BackgroundMarker;
 #[derive(Bundle)]
 pub struct Background {
     marker: BackgroundMarker,
-    size: DefaultSize,
-    location: Location,
     #[bundle]
     sprite: SpriteBundle,
 }
 
 pub fn init_background(mut commands: Commands, material: Res<MaterialResource>) {
     let sprite = SpriteBundle {
-        sprite: Default::default(),
+        sprite: Sprite::new(Vec2::new(ARENA_SIZE, ARENA_SIZE)),
         material: material.background_material.clone(),
         transform: Transform::from_xyz(0.0, 0.0, -0.01),
         ..Default::default()
     };
     commands.spawn_bundle(Background {
         marker: BackgroundMarker,
-        size: DefaultSize {
-            width: ARENA_SIZE,
-            height: ARENA_SIZE,
-        },
-        location: Location(Vec2::new(0.0, 0.0)),
         sprite,
     });
 }
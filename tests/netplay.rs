@@ -0,0 +1,103 @@
+// Runs two netplay peers over loopback in one process and checks that both feed the same switches
+// to their simulations.
+#![cfg(feature = "netplay")]
+use bevy::prelude::*;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+use switch::net::{begin_net_run, exchange_inputs, NetConfig, NetSession, NetStatus};
+use switch::rng::{GameRng, RunSeed};
+use switch::sim::{
+    InputCommand, InputQueue, NetInput, SimClock, SimConfig, TickGate, DEFAULT_TICK_RATE,
+};
+
+const STEPS: usize = 400;
+const SWITCH_EVERY: usize = 7;
+const SWITCHES: usize = 20;
+
+fn free_address() -> SocketAddr {
+    UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+fn peer(bind: SocketAddr, peer: SocketAddr, slot: u8, seed: u64) -> World {
+    let config = NetConfig { bind, peer, slot };
+    let mut world = World::new();
+    world.insert_resource(NetSession::open(&config).expect("could not open the socket"));
+    world.insert_resource(SimClock::new(DEFAULT_TICK_RATE));
+    world.insert_resource(SimConfig {
+        players: 2,
+        ..SimConfig::default()
+    });
+    world.insert_resource(RunSeed(seed));
+    world.insert_resource(GameRng::new(seed));
+    world.insert_resource(NetInput {
+        local_slot: Some(slot),
+        outgoing: Vec::new(),
+    });
+    world.insert_resource(InputQueue::default());
+    world.insert_resource(TickGate::default());
+    SystemStage::single(begin_net_run.system()).run(&mut world);
+    world
+}
+
+fn queued(world: &World) -> Vec<(u64, u8, [f32; 2])> {
+    world
+        .get_resource::<InputQueue>()
+        .unwrap()
+        .0
+        .iter()
+        .map(|command| (command.tick, command.player, command.aim))
+        .collect()
+}
+
+#[test]
+fn peers_release_the_same_switches() {
+    let (first, second) = (free_address(), free_address());
+    let mut peers = [peer(first, second, 0, 7), peer(second, first, 1, 99)];
+    let mut stages = [
+        SystemStage::single(exchange_inputs.system()),
+        SystemStage::single(exchange_inputs.system()),
+    ];
+    for step in 0..STEPS {
+        for (slot, (world, stage)) in peers.iter_mut().zip(stages.iter_mut()).enumerate() {
+            if step % SWITCH_EVERY == slot && step / SWITCH_EVERY < SWITCHES {
+                let aim = Vec2::new(step as f32, slot as f32);
+                world
+                    .get_resource_mut::<NetInput>()
+                    .unwrap()
+                    .outgoing
+                    .push(InputCommand::new(0, slot as u8, aim));
+            }
+            stage.run(world);
+            // The simulation runs every tick the gate lets through.
+            let gate = world.get_resource::<TickGate>().unwrap().0.unwrap();
+            world.get_resource_mut::<SimClock>().unwrap().tick = gate;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    for world in peers.iter() {
+        assert_eq!(
+            world.get_resource::<NetSession>().unwrap().status,
+            NetStatus::Running
+        );
+        assert_eq!(world.get_resource::<RunSeed>().unwrap().0, 7);
+    }
+    let (host, client) = (queued(&peers[0]), queued(&peers[1]));
+    assert_eq!(host.len(), SWITCHES * 2);
+    assert_eq!(host, client);
+    for slot in 0..2u8 {
+        let aims: Vec<f32> = host
+            .iter()
+            .filter(|(_, player, _)| *player == slot)
+            .map(|(_, _, aim)| aim[0])
+            .collect();
+        let sent: Vec<f32> = (0..STEPS)
+            .filter(|step| step % SWITCH_EVERY == slot as usize && step / SWITCH_EVERY < SWITCHES)
+            .map(|step| step as f32)
+            .collect();
+        assert_eq!(aims, sent);
+    }
+}
@@ -0,0 +1,107 @@
+// Records a seeded run with the systems the game runs around the simulation, then checks that the
+// verifier replays the recording to the same end.
+use bevy::core::CorePlugin;
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use switch::daily::DailyPlugin;
+use switch::enemies::update_mine_rings;
+use switch::leaderboard::{LastRun, LeaderboardPlugin};
+use switch::menu::MenuEvent;
+use switch::palette::ActivePalette;
+use switch::particles::ParticlePlugin;
+use switch::protocol::{verify, Submission, PROTOCOL_VERSION};
+use switch::settings::Settings;
+use switch::sim::{simulate, FixedSeed, InputQueue, SimClock, SimDriver, SimPlugin};
+use switch::ui::{init_hud, Fonts};
+use switch::GameState;
+
+const SEED: u64 = 7;
+const PRESS_INTERVAL: u64 = 20;
+const MAX_TICKS: u64 = 60 * 60 * 10;
+
+// The simulation with the particles, mine rings, HUD and game over screen the game adds, minus
+// the window and renderer.
+fn game_app() -> App {
+    let mut builder = App::build();
+    builder
+        .add_plugin(CorePlugin)
+        .add_plugin(InputPlugin)
+        // Read by the game over screen, which also needs the menus and a window in the game.
+        .add_event::<MenuEvent>()
+        .add_event::<ReceivedCharacter>()
+        .insert_resource(Settings::default())
+        .insert_resource(SimDriver::Headless { max_ticks: 0 })
+        .insert_resource(FixedSeed(Some(SEED)))
+        .insert_resource(Fonts {
+            font: Handle::default(),
+        })
+        .init_resource::<ActivePalette>()
+        .add_plugin(SimPlugin)
+        .add_plugin(ParticlePlugin)
+        .add_plugin(LeaderboardPlugin)
+        .add_plugin(DailyPlugin)
+        .add_state(GameState::Game)
+        .add_system_set(SystemSet::on_enter(GameState::Game).with_system(init_hud.system()))
+        .add_system_set(
+            SystemSet::on_update(GameState::Game).with_system(update_mine_rings.system()),
+        );
+    builder.app
+}
+
+// Presses every `PRESS_INTERVAL` ticks, aiming around the arena.
+fn queue_presses(queue: &mut InputQueue) {
+    for press in 1..MAX_TICKS / PRESS_INTERVAL {
+        let aim = Vec2::new(
+            ((press * 97) % 400) as f32 - 200f32,
+            ((press * 53) % 400) as f32 - 200f32,
+        );
+        queue.push(press * PRESS_INTERVAL, 0, aim);
+    }
+}
+
+fn in_game(app: &App) -> bool {
+    *app.world
+        .get_resource::<State<GameState>>()
+        .unwrap()
+        .current()
+        == GameState::Game
+}
+
+fn recorded_ticks(app: &App) -> u64 {
+    app.world.get_resource::<SimClock>().unwrap().tick
+}
+
+#[test]
+fn recorded_runs_verify() {
+    let mut app = game_app();
+    // Enters the game, which clears the queue, without running a tick.
+    app.update();
+    queue_presses(&mut app.world.get_resource_mut::<InputQueue>().unwrap());
+    // One tick a frame, like the game at its tick rate.
+    while in_game(&app) {
+        let tick = recorded_ticks(&app);
+        assert!(tick < MAX_TICKS, "the run should end");
+        *app.world.get_resource_mut::<SimDriver>().unwrap() = SimDriver::Headless {
+            max_ticks: tick + 1,
+        };
+        app.update();
+    }
+
+    let ticks = recorded_ticks(&app);
+    let last_run = app.world.get_resource::<LastRun>().unwrap();
+    assert!(!last_run.recording.inputs.is_empty());
+    let result = simulate(&last_run.recording, MAX_TICKS);
+    assert!(result.finished);
+    assert_eq!(result.ticks, ticks);
+    assert_eq!(result.score, last_run.score);
+
+    let verdict = verify(&Submission {
+        version: PROTOCOL_VERSION,
+        name: "Test".to_string(),
+        replay: last_run.recording.clone(),
+        claimed_time: last_run.time,
+        claimed_score: last_run.score,
+    });
+    assert!(verdict.accepted, "rejected: {:?}", verdict.reason);
+    assert_eq!(verdict.score, last_run.score);
+}
@@ -0,0 +1,140 @@
+// Runs the reference verifier as a mock leaderboard server on a free port and submits runs to it.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use switch::protocol::{Submission, Verdict, PROTOCOL_VERSION, SUBMIT_PATH};
+use switch::sim::{simulate, Replay, SimConfig};
+
+const MAX_TICKS: u64 = 60 * 60 * 60;
+
+struct Server {
+    child: Child,
+    address: String,
+}
+
+impl Server {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_verifier"))
+            .args(&["--serve", "127.0.0.1:0"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("could not start the verifier");
+        let mut line = String::new();
+        BufReader::new(child.stdout.as_mut().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let address = line
+            .trim()
+            .strip_prefix("listening on ")
+            .expect("the verifier did not report its address")
+            .to_string();
+        Server { child, address }
+    }
+
+    // Sends one request and returns the status code and body of the response.
+    fn request(&self, method: &str, path: &str, headers: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\n{}\r\n{}",
+            method, path, self.address, headers, body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map_or("", |(_, body)| body)
+            .to_string();
+        (status, body)
+    }
+
+    fn submit(&self, submission: &Submission) -> Verdict {
+        let body = serde_json::to_string(submission).unwrap();
+        let headers = format!(
+            "Content-Type: application/json\r\nContent-Length: {}\r\n",
+            body.len()
+        );
+        let (status, body) = self.request("POST", SUBMIT_PATH, &headers, &body);
+        assert_eq!(status, 200);
+        serde_json::from_str(&body).unwrap()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.child.kill().unwrap_or(());
+        self.child.wait().ok();
+    }
+}
+
+// A run where the player never presses anything, with the claims it really ends with.
+fn honest_submission() -> Submission {
+    let replay = Replay {
+        config: SimConfig::default(),
+        seed: 7,
+        ..Default::default()
+    };
+    let result = simulate(&replay, MAX_TICKS);
+    assert!(
+        result.finished,
+        "the idle player should not outlive the test"
+    );
+    Submission {
+        version: PROTOCOL_VERSION,
+        name: "Test".to_string(),
+        replay,
+        claimed_time: result.time,
+        claimed_score: result.score,
+    }
+}
+
+#[test]
+fn accepts_and_rejects_submissions() {
+    let server = Server::start();
+    let honest = honest_submission();
+
+    let verdict = server.submit(&honest);
+    assert!(verdict.accepted, "rejected: {:?}", verdict.reason);
+
+    let longer = Submission {
+        claimed_time: honest.claimed_time + 5f32,
+        ..honest.clone()
+    };
+    assert!(!server.submit(&longer).accepted);
+
+    let higher = Submission {
+        claimed_score: honest.claimed_score + 1,
+        ..honest.clone()
+    };
+    assert!(!server.submit(&higher).accepted);
+
+    let mut fast = honest.clone();
+    fast.replay.config.tick_rate *= 1000;
+    assert!(!server.submit(&fast).accepted);
+
+    let mut alone = honest.clone();
+    alone.replay.config.enemies = false;
+    assert!(!server.submit(&alone).accepted);
+}
+
+#[test]
+fn rejects_oversized_bodies() {
+    let server = Server::start();
+    let (status, _) = server.request("POST", SUBMIT_PATH, "Content-Length: 1000000000000\r\n", "");
+    assert_eq!(status, 413);
+}
+
+#[test]
+fn serves_others_while_a_client_stalls() {
+    let server = Server::start();
+    let mut stalled = TcpStream::connect(&server.address).unwrap();
+    write!(stalled, "POST {} HTTP/1.1\r\n", SUBMIT_PATH).unwrap();
+    let verdict = server.submit(&honest_submission());
+    assert!(verdict.accepted, "rejected: {:?}", verdict.reason);
+}